@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::{BillReport, Totals};
+
+/// The billed figures of a report as saved by `--save-baseline`, for
+/// `--diff-baseline` to check later runs against.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Baseline {
+    /// The client the report is of, empty in baselines saved before it was kept.
+    #[serde(default)]
+    client: String,
+    days: BTreeMap<String, BaselineDay>,
+    minutes: i64,
+    amount: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+struct BaselineDay {
+    billed_minutes: i64,
+    billed_amount: f64,
+}
+
+impl Baseline {
+    pub fn new(client_name: &str, bill_report: &BillReport, totals: &Totals) -> Self {
+        Self {
+            client: client_name.to_string(),
+            days: bill_report
+                .days
+                .iter()
+                .map(|day| {
+                    let billed = BaselineDay {
+                        billed_minutes: day.billed_minutes,
+                        billed_amount: day.billed_amount,
+                    };
+                    (day.date.clone(), billed)
+                })
+                .collect(),
+            minutes: totals.minutes,
+            amount: totals.amount,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Loads the baseline at `path`, which must be of the given client.
+    pub fn load(path: &Path, client_name: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let baseline: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse baseline {}", path.display()))?;
+        if !baseline.client.is_empty() && baseline.client != client_name {
+            anyhow::bail!(
+                "Baseline {} is of client {}, not {}",
+                path.display(),
+                baseline.client,
+                client_name
+            );
+        }
+        Ok(baseline)
+    }
+
+    /// A line per day billed differently than in the baseline, and one for the
+    /// totals when they changed.
+    pub fn diff(&self, current: &Baseline) -> Vec<String> {
+        let dates: BTreeSet<&String> = self.days.keys().chain(current.days.keys()).collect();
+        let mut lines: Vec<String> = dates
+            .into_iter()
+            .filter_map(|date| match (self.days.get(date), current.days.get(date)) {
+                (Some(before), Some(after)) if before != after => Some(format!(
+                    "{}: {}",
+                    date,
+                    delta(
+                        (before.billed_minutes, before.billed_amount),
+                        (after.billed_minutes, after.billed_amount)
+                    )
+                )),
+                (Some(before), None) => Some(format!(
+                    "{}: removed, was {} minutes, {:.2}",
+                    date, before.billed_minutes, before.billed_amount
+                )),
+                (None, Some(after)) => Some(format!(
+                    "{}: added, {} minutes, {:.2}",
+                    date, after.billed_minutes, after.billed_amount
+                )),
+                _ => None,
+            })
+            .collect();
+        if (self.minutes, self.amount) != (current.minutes, current.amount) {
+            lines.push(format!(
+                "Total: {}",
+                delta(
+                    (self.minutes, self.amount),
+                    (current.minutes, current.amount)
+                )
+            ));
+        }
+
+        lines
+    }
+}
+
+/// E.g. `60 -> 120 minutes (+60), 30.00 -> 60.00 (+30.00)`.
+fn delta((minutes, amount): (i64, f64), (new_minutes, new_amount): (i64, f64)) -> String {
+    format!(
+        "{} -> {} minutes ({:+}), {:.2} -> {:.2} ({:+.2})",
+        minutes,
+        new_minutes,
+        new_minutes - minutes,
+        amount,
+        new_amount,
+        new_amount - amount
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_bill_report, calculate_totals, Client, Summary};
+
+    fn baseline(days: &[(&str, i64)]) -> Baseline {
+        let summary: Summary = days
+            .iter()
+            .map(|(date, minutes)| (date.to_string(), minutes * 60))
+            .collect();
+        let client = Client {
+            hourly_rate: Some(30.0),
+            last_billed_date: "2022-01-01".to_string(),
+            ..Default::default()
+        };
+        let bill_report = build_bill_report(summary, None, None, &client);
+        Baseline::new(
+            "acme",
+            &bill_report,
+            &calculate_totals(&bill_report, &client),
+        )
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = baseline(&[("2022-01-02", 25), ("2022-01-03", 80)]);
+        let after = baseline(&[("2022-01-02", 25), ("2022-01-03", 130)]);
+
+        assert!(before.diff(&before).is_empty());
+        assert_eq!(
+            vec![
+                "2022-01-03: 120 -> 130 minutes (+10), 60.00 -> 65.00 (+5.00)".to_string(),
+                "Total: 180 -> 190 minutes (+10), 90.00 -> 120.00 (+30.00)".to_string(),
+            ],
+            before.diff(&after)
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("toggl-baseline-{}.json", std::process::id()));
+        let saved = baseline(&[("2022-01-02", 25)]);
+
+        saved.save(&path).unwrap();
+        let loaded = Baseline::load(&path, "acme");
+        let other = Baseline::load(&path, "globex");
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(saved, loaded.unwrap());
+        assert_eq!(
+            format!("Baseline {} is of client acme, not globex", path.display()),
+            other.unwrap_err().to_string()
+        );
+    }
+}
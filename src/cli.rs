@@ -0,0 +1,1024 @@
+use anyhow::{Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::locale::Locale;
+use crate::pattern::Pattern;
+use crate::rounding::Rounding;
+
+/// Columns of the report's table `--columns` can pick from.
+const COLUMNS: &[&str] = &[
+    "date",
+    "actual_minutes",
+    "billed_minutes",
+    "billed_amount",
+    "billed",
+    "cumulative_amount",
+    "entries",
+    "carried_minutes",
+    "over_cap_minutes",
+];
+
+/// Shortest `--watch` interval allowed, to stay well clear of Toggl's rate limits.
+const MIN_WATCH_SECONDS: u64 = 10;
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Report(Box<Options>),
+    AddClient(AddClientOptions),
+    /// Fill in missing client ids from the Toggl clients of the same name.
+    SyncClients(ConfigOptions),
+    MigrateConfig(ConfigOptions),
+    ShowConfig(ShowConfigOptions),
+    /// Validate the config without fetching anything, for CI.
+    CheckConfig(ConfigOptions),
+}
+
+impl Command {
+    pub fn parse<I>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter().peekable();
+
+        if args.peek().map(String::as_str) == Some("clients") {
+            args.next();
+            return match args.next().as_deref() {
+                Some("add") => Ok(Command::AddClient(AddClientOptions::parse(args)?)),
+                Some("sync") => Ok(Command::SyncClients(ConfigOptions::parse(args)?)),
+                Some(other) => anyhow::bail!("Unknown clients subcommand: {}", other),
+                None => anyhow::bail!("Missing clients subcommand"),
+            };
+        }
+
+        if args.peek().map(String::as_str) == Some("config") {
+            args.next();
+            return match args.next().as_deref() {
+                Some("migrate") => Ok(Command::MigrateConfig(ConfigOptions::parse(args)?)),
+                Some("show") => Ok(Command::ShowConfig(ShowConfigOptions::parse(args)?)),
+                Some("check") => Ok(Command::CheckConfig(ConfigOptions::parse(args)?)),
+                Some(other) => anyhow::bail!("Unknown config subcommand: {}", other),
+                None => anyhow::bail!("Missing config subcommand"),
+            };
+        }
+
+        Ok(Command::Report(Box::new(Options::parse(args)?)))
+    }
+
+    /// The config file given with `--config`, `./config.toml` is used without.
+    pub fn config(&self) -> Option<&Path> {
+        match self {
+            Command::Report(options) => options.config.as_deref(),
+            Command::AddClient(options) => options.config.as_deref(),
+            Command::SyncClients(options)
+            | Command::MigrateConfig(options)
+            | Command::CheckConfig(options) => options.config.as_deref(),
+            Command::ShowConfig(options) => options.config.as_deref(),
+        }
+    }
+}
+
+/// Options of `config show`.
+#[derive(Debug, Default, PartialEq)]
+pub struct ShowConfigOptions {
+    pub config: Option<PathBuf>,
+    pub json: bool,
+}
+
+impl ShowConfigOptions {
+    fn parse<I>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = ShowConfigOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => options.config = Some(parse_value(&arg, args.next())?),
+                "--json" => options.json = true,
+                flag if flag.starts_with("--") => anyhow::bail!("Unknown option: {}", flag),
+                _ => anyhow::bail!("Unexpected argument: {}", arg),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Options of `config check`, `config migrate` and `clients sync`.
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigOptions {
+    pub config: Option<PathBuf>,
+}
+
+impl ConfigOptions {
+    fn parse<I>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = ConfigOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => options.config = Some(parse_value(&arg, args.next())?),
+                flag if flag.starts_with("--") => anyhow::bail!("Unknown option: {}", flag),
+                _ => anyhow::bail!("Unexpected argument: {}", arg),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Values for `clients add`, anything left out is prompted for.
+#[derive(Debug, Default, PartialEq)]
+pub struct AddClientOptions {
+    pub name: Option<String>,
+    pub id: Option<String>,
+    pub hourly_rate: Option<String>,
+    pub currency: Option<String>,
+    pub last_billed_date: Option<String>,
+    pub force: bool,
+    pub config: Option<PathBuf>,
+}
+
+impl AddClientOptions {
+    fn parse<I>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = AddClientOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--id" => options.id = Some(parse_value(&arg, args.next())?),
+                "--rate" => options.hourly_rate = Some(parse_value(&arg, args.next())?),
+                "--currency" => options.currency = Some(parse_value(&arg, args.next())?),
+                "--last-billed" => options.last_billed_date = Some(parse_value(&arg, args.next())?),
+                "--force" => options.force = true,
+                "--config" => options.config = Some(parse_value(&arg, args.next())?),
+                flag if flag.starts_with("--") => anyhow::bail!("Unknown option: {}", flag),
+                _ if options.name.is_none() => options.name = Some(arg),
+                _ => anyhow::bail!("Unexpected argument: {}", arg),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// How the report is written to stdout.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Format {
+    #[default]
+    Table,
+    /// See [`crate::json`] for the shape.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            _ => anyhow::bail!("Unknown format: {}, expected table or json", s),
+        }
+    }
+}
+
+/// Range of days `--period` reports on, relative to today.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    ThisMonth,
+    LastMonth,
+    ThisYear,
+    LastYear,
+    /// Today and the six days before it.
+    Last7Days,
+}
+
+impl Period {
+    /// The first and last day of the period, given today's date.
+    pub fn range(self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let first_of_month = today.with_day(1).unwrap();
+        let first_of_year = today.with_ordinal(1).unwrap();
+        match self {
+            Period::ThisMonth => (first_of_month, today),
+            Period::LastMonth => {
+                let last_day = first_of_month.pred_opt().unwrap();
+                (last_day.with_day(1).unwrap(), last_day)
+            }
+            Period::ThisYear => (first_of_year, today),
+            Period::LastYear => {
+                let last_day = first_of_year.pred_opt().unwrap();
+                (last_day.with_ordinal(1).unwrap(), last_day)
+            }
+            Period::Last7Days => (today - Duration::days(6), today),
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "this-month" => Ok(Period::ThisMonth),
+            "last-month" => Ok(Period::LastMonth),
+            "this-year" => Ok(Period::ThisYear),
+            "last-year" => Ok(Period::LastYear),
+            "last-7-days" => Ok(Period::Last7Days),
+            _ => anyhow::bail!(
+                "Unknown --period: {}, expected this-month, last-month, this-year, last-year or last-7-days",
+                s
+            ),
+        }
+    }
+}
+
+/// Column `--sort` orders the report's table by, totals aren't affected.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    #[default]
+    Date,
+    /// Billed minutes.
+    Minutes,
+    /// Billed amount.
+    Amount,
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "date" => Ok(SortKey::Date),
+            "minutes" => Ok(SortKey::Minutes),
+            "amount" => Ok(SortKey::Amount),
+            _ => anyhow::bail!("Unknown --sort: {}, expected date, minutes or amount", s),
+        }
+    }
+}
+
+/// What `--overlaps` does with entries overlapping an earlier entry.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Overlaps {
+    /// Bill both entries in full and print a warning.
+    #[default]
+    Warn,
+    Error,
+    /// Bill the union of the entries, counting the overlap once.
+    Merge,
+    /// Bill only the longer entry.
+    Dedup,
+}
+
+impl FromStr for Overlaps {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "warn" => Ok(Overlaps::Warn),
+            "error" => Ok(Overlaps::Error),
+            "merge" => Ok(Overlaps::Merge),
+            "dedup" => Ok(Overlaps::Dedup),
+            _ => anyhow::bail!(
+                "Unknown --overlaps: {}, expected warn, error, merge or dedup",
+                s
+            ),
+        }
+    }
+}
+
+/// Which days `--attribute` bills entries spanning midnight to.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Attribution {
+    /// All of the entry to the day it started.
+    #[default]
+    Start,
+    /// Each part of the entry to the day it falls on.
+    Split,
+}
+
+impl FromStr for Attribution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "start" => Ok(Attribution::Start),
+            "split" => Ok(Attribution::Split),
+            _ => anyhow::bail!("Unknown --attribute: {}, expected start or split", s),
+        }
+    }
+}
+
+/// What `--group-by` builds a cross-cutting table over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    /// One row per configured client.
+    Client,
+    /// One row per week of the client's report, see `week_start`.
+    Week,
+}
+
+impl FromStr for GroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "client" => Ok(GroupBy::Client),
+            "week" => Ok(GroupBy::Week),
+            _ => anyhow::bail!("Unknown --group-by: {}, expected client or week", s),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    /// Clients to report on, several get a section each and a combined total.
+    pub client_names: Vec<String>,
+    pub min_amount: Option<f64>,
+    pub watch: Option<u64>,
+    pub strict: bool,
+    pub billable_only: bool,
+    pub html: Option<PathBuf>,
+    pub pdf: Option<PathBuf>,
+    /// The number in the header of `--html` and `--pdf` invoices, the issue date
+    /// like 20220131 by default.
+    pub invoice_number: Option<String>,
+    pub group_format: Option<String>,
+    pub verbose: bool,
+    pub invoice_minimum_minutes: Option<i64>,
+    pub locale: Option<Locale>,
+    pub entries: bool,
+    pub note: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    /// Named range resolved into `since` and `until` when parsing.
+    pub period: Option<Period>,
+    /// Bill the `--since`/`--until` window again, even if it was billed before.
+    pub rebill: bool,
+    /// Request timeout in seconds, overriding the config.
+    pub timeout: Option<u64>,
+    /// Only bill entries of this task, `(no task)` for entries without one.
+    pub task: Option<String>,
+    pub by_task: bool,
+    pub by_project: bool,
+    /// Explain how every figure of the report was derived.
+    pub explain: bool,
+    /// Show tracked time per day of the week instead of the report.
+    pub by_weekday: bool,
+    /// Show tracked time per category, the first group the pattern captures from
+    /// each entry's description, instead of the report.
+    pub categorize: Option<Pattern>,
+    pub format: Format,
+    /// Leave the amounts out of the report.
+    pub compact: bool,
+    /// Show durations as `H:MM:SS` instead of whole minutes.
+    pub show_seconds: bool,
+    pub group_by: Option<GroupBy>,
+    /// Config file to use instead of `./config.toml`, `-` to read it from stdin.
+    pub config: Option<PathBuf>,
+    /// UTC offset to bucket entries into days by, instead of each entry's own.
+    pub timezone: Option<FixedOffset>,
+    /// Bill the minutes tracked instead of the one hour day minimum.
+    pub prorate_minimums: bool,
+    /// Count running entries up to now instead of leaving them out.
+    pub assume_end_now: bool,
+    pub sort: SortKey,
+    /// Sort the table in descending order.
+    pub desc: bool,
+    pub overlaps: Overlaps,
+    pub attribute: Attribution,
+    /// Merge entries of a project less than this many minutes apart.
+    pub merge_gap: Option<u32>,
+    /// Print the tracked minutes per day as JSON instead of the report.
+    pub summary_json: bool,
+    /// Details responses saved from Toggl to bill instead of fetching entries.
+    pub from_files: Vec<PathBuf>,
+    /// Hide client names and amounts, e.g. to share a report.
+    pub redact: bool,
+    pub no_progress: bool,
+    /// Rounding to compare the client's totals with, without changing its config.
+    pub preview_rounding: Option<Rounding>,
+    /// Save the billed figures to compare later runs against with `--diff-baseline`.
+    pub save_baseline: Option<PathBuf>,
+    /// Print how the billed figures changed since `--save-baseline` instead of the
+    /// report.
+    pub diff_baseline: Option<PathBuf>,
+    /// Also write every fetched entry to this CSV file, see `ENTRIES_CSV_COLUMNS`.
+    pub entries_csv: Option<PathBuf>,
+    /// Write a report file per client into this directory, every configured client
+    /// unless some are given.
+    pub output_dir: Option<PathBuf>,
+    /// Sum the time tracked in every workspace instead of billing a client.
+    pub all_workspaces: bool,
+    /// Keep the years fetched so far, so a failed pull resumes where it stopped.
+    pub resume: bool,
+    /// Most years to fetch, raising the default guard against huge pulls.
+    pub max_years: Option<usize>,
+    /// Bill the amounts Toggl computed from the workspace's rates.
+    pub use_toggl_amounts: bool,
+    /// Keep entries ending when they start instead of dropping them.
+    pub keep_zero: bool,
+    /// Add a column with the running total of the unbilled amount.
+    pub cumulative: bool,
+    /// Columns of the table in the order given, instead of the default ones.
+    pub columns: Option<Vec<String>>,
+    /// Estimate the current month's and year's totals from the run rate so far.
+    pub forecast: bool,
+    /// Add a column with the number of entries tracked each day.
+    pub entry_counts: bool,
+    /// Print just the total amount, for scripts.
+    pub quiet: bool,
+    /// Move the client's `last_billed_date` in the config to its last unbilled day.
+    pub mark_billed: bool,
+    /// With `--mark-billed`, only show what would change.
+    pub dry_run: bool,
+}
+
+impl Options {
+    fn parse<I>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--min-amount" => options.min_amount = Some(parse_value(&arg, args.next())?),
+                "--watch" => {
+                    let seconds = parse_value(&arg, args.next())?;
+                    if seconds < MIN_WATCH_SECONDS {
+                        anyhow::bail!(
+                            "--watch interval must be at least {} seconds",
+                            MIN_WATCH_SECONDS
+                        );
+                    }
+                    options.watch = Some(seconds);
+                }
+                "--strict" => options.strict = true,
+                "--billable-only" => options.billable_only = true,
+                "--verbose" => options.verbose = true,
+                "--entries" => options.entries = true,
+                "--note" => options.note = Some(parse_value(&arg, args.next())?),
+                "--since" => options.since = Some(parse_value(&arg, args.next())?),
+                "--until" => options.until = Some(parse_value(&arg, args.next())?),
+                "--period" => {
+                    let period: String = parse_value(&arg, args.next())?;
+                    options.period = Some(period.parse()?);
+                }
+                "--rebill" => options.rebill = true,
+                "--task" => options.task = Some(parse_value(&arg, args.next())?),
+                "--by-task" => options.by_task = true,
+                "--by-project" => options.by_project = true,
+                "--explain" => options.explain = true,
+                "--by-weekday" => options.by_weekday = true,
+                "--categorize" => {
+                    let pattern: String = parse_value(&arg, args.next())?;
+                    options.categorize = Some(pattern.parse()?);
+                }
+                "--compact" => options.compact = true,
+                "--show-seconds" => options.show_seconds = true,
+                "--prorate-minimums" => options.prorate_minimums = true,
+                "--assume-end-now" => options.assume_end_now = true,
+                "--desc" => options.desc = true,
+                "--summary-json" => options.summary_json = true,
+                "--redact" => options.redact = true,
+                "--no-progress" => options.no_progress = true,
+                "--quiet" => options.quiet = true,
+                "--mark-billed" => options.mark_billed = true,
+                "--dry-run" => options.dry_run = true,
+                "--cumulative" => options.cumulative = true,
+                "--entry-counts" => options.entry_counts = true,
+                "--forecast" => options.forecast = true,
+                "--keep-zero" => options.keep_zero = true,
+                "--resume" => options.resume = true,
+                "--all-workspaces" => options.all_workspaces = true,
+                "--use-toggl-amounts" => options.use_toggl_amounts = true,
+                "--preview-rounding" => {
+                    let rounding: String = parse_value(&arg, args.next())?;
+                    options.preview_rounding = Some(rounding.parse()?);
+                }
+                "--from-file" | "--fixture" => {
+                    options.from_files.push(parse_value(&arg, args.next())?)
+                }
+                "--attribute" => {
+                    let attribute: String = parse_value(&arg, args.next())?;
+                    options.attribute = attribute.parse()?;
+                }
+                "--overlaps" => {
+                    let overlaps: String = parse_value(&arg, args.next())?;
+                    options.overlaps = overlaps.parse()?;
+                }
+                "--save-baseline" => options.save_baseline = Some(parse_value(&arg, args.next())?),
+                "--diff-baseline" => options.diff_baseline = Some(parse_value(&arg, args.next())?),
+                "--output-dir" => options.output_dir = Some(parse_value(&arg, args.next())?),
+                "--entries-csv" => options.entries_csv = Some(parse_value(&arg, args.next())?),
+                "--max-years" => options.max_years = Some(parse_value(&arg, args.next())?),
+                "--merge-gap" => options.merge_gap = Some(parse_value(&arg, args.next())?),
+                "--columns" => {
+                    let columns: String = parse_value(&arg, args.next())?;
+                    options.columns = Some(parse_columns(&columns)?);
+                }
+                "--sort" => {
+                    let sort: String = parse_value(&arg, args.next())?;
+                    options.sort = sort.parse()?;
+                }
+                "--config" => options.config = Some(parse_value(&arg, args.next())?),
+                "--timezone" => {
+                    let timezone: String = parse_value(&arg, args.next())?;
+                    options.timezone = Some(parse_offset(&timezone)?);
+                }
+                "--group-by" => {
+                    let group_by: String = parse_value(&arg, args.next())?;
+                    options.group_by = Some(group_by.parse()?);
+                }
+                "--format" => {
+                    let format: String = parse_value(&arg, args.next())?;
+                    options.format = format.parse()?;
+                }
+                "--timeout" => {
+                    let seconds = parse_value(&arg, args.next())?;
+                    if seconds == 0 {
+                        anyhow::bail!("--timeout must be at least 1 second");
+                    }
+                    options.timeout = Some(seconds);
+                }
+                "--locale" => {
+                    let locale: String = parse_value(&arg, args.next())?;
+                    options.locale = Some(locale.parse()?);
+                }
+                "--round-to-invoice-minimum" => {
+                    options.invoice_minimum_minutes = Some(parse_value(&arg, args.next())?)
+                }
+                "--html" => options.html = Some(parse_value(&arg, args.next())?),
+                "--pdf" => options.pdf = Some(parse_value(&arg, args.next())?),
+                "--invoice-number" => {
+                    options.invoice_number = Some(parse_value(&arg, args.next())?)
+                }
+                "--group-format" => {
+                    let group_format: String = parse_value(&arg, args.next())?;
+                    validate_group_format(&group_format)?;
+                    options.group_format = Some(group_format);
+                }
+                flag if flag.starts_with("--") => anyhow::bail!("Unknown option: {}", flag),
+                _ => options.client_names.push(arg),
+            }
+        }
+
+        if let Some(period) = options.period {
+            if options.since.is_some() || options.until.is_some() {
+                anyhow::bail!("--period can't be combined with --since or --until");
+            }
+            let (since, until) = period.range(chrono::Local::now().date_naive());
+            options.since = Some(since);
+            options.until = Some(until);
+        }
+        if let (Some(since), Some(until)) = (options.since, options.until) {
+            if since > until {
+                anyhow::bail!("--since {} is after --until {}", since, until);
+            }
+        }
+        if options.group_by == Some(GroupBy::Week) && options.group_format.is_some() {
+            anyhow::bail!("--group-by week can't be combined with --group-format");
+        }
+        if options.group_by == Some(GroupBy::Client)
+            && (options.format != Format::Table || options.html.is_some() || options.pdf.is_some())
+        {
+            anyhow::bail!(
+                "--group-by client can't be combined with --format json, --html or --pdf"
+            );
+        }
+        // --html and --pdf name a single file, --output-dir writes one per client
+        if options.output_dir.is_some() && (options.html.is_some() || options.pdf.is_some()) {
+            anyhow::bail!("--output-dir can't be combined with --html or --pdf");
+        }
+        if options.redact && (options.html.is_some() || options.pdf.is_some()) {
+            anyhow::bail!("--redact can't be combined with --html or --pdf");
+        }
+        if options.invoice_number.is_some() && options.html.is_none() && options.pdf.is_none() {
+            anyhow::bail!("--invoice-number requires --html or --pdf");
+        }
+        if options.quiet && (options.explain || options.format == Format::Json) {
+            anyhow::bail!("--quiet can't be combined with --explain or --format json");
+        }
+        // the amount is all --quiet prints, there'd be nothing left after masking it
+        if options.quiet && options.redact {
+            anyhow::bail!("--quiet can't be combined with --redact");
+        }
+        if options.quiet
+            && (options.entries
+                || options.by_task
+                || options.by_project
+                || options.by_weekday
+                || options.categorize.is_some()
+                || options.summary_json)
+        {
+            anyhow::bail!(
+                "--quiet can't be combined with --entries, --by-task, --by-project, --by-weekday, --categorize or --summary-json"
+            );
+        }
+        if options.entries_csv.is_some()
+            && (options.client_names.len() > 1
+                || options.group_by == Some(GroupBy::Client)
+                || options.output_dir.is_some())
+        {
+            anyhow::bail!("--entries-csv takes the entries of a single client");
+        }
+        if (options.save_baseline.is_some() || options.diff_baseline.is_some())
+            && (options.client_names.len() > 1
+                || options.group_by == Some(GroupBy::Client)
+                || options.output_dir.is_some())
+        {
+            anyhow::bail!("--save-baseline and --diff-baseline take a single client");
+        }
+        // several clients are written as one combined plain text report
+        if options.client_names.len() > 1
+            && options.output_dir.is_none()
+            && (options.format != Format::Table
+                || options.html.is_some()
+                || options.pdf.is_some()
+                || options.explain
+                || options.forecast
+                || options.entries
+                || options.by_task
+                || options.by_project
+                || options.by_weekday
+                || options.categorize.is_some()
+                || options.summary_json)
+        {
+            anyhow::bail!(
+                "--format json, --html, --pdf, --explain, --forecast, --entries, --by-task, --by-project, --by-weekday, --categorize and --summary-json take a single client"
+            );
+        }
+        if options.mark_billed
+            && (options.rebill
+                || options.group_by.is_some()
+                || options.group_format.is_some()
+                || options.client_names.len() > 1)
+        {
+            anyhow::bail!(
+                "--mark-billed takes a single client, without --rebill, --group-by or --group-format"
+            );
+        }
+        if options.mark_billed
+            && (options.format != Format::Table
+                || options.html.is_some()
+                || options.pdf.is_some()
+                || options.output_dir.is_some())
+        {
+            anyhow::bail!(
+                "--mark-billed can't be combined with --format, --html, --pdf or --output-dir"
+            );
+        }
+        if options.dry_run && !options.mark_billed {
+            anyhow::bail!("--dry-run requires --mark-billed");
+        }
+        if options.rebill && (options.since.is_none() || options.until.is_none()) {
+            anyhow::bail!("--rebill requires both --since and --until");
+        }
+
+        Ok(options)
+    }
+}
+
+/// Splits a comma-separated list of table columns, checking each is known.
+fn parse_columns(columns: &str) -> Result<Vec<String>> {
+    columns
+        .split(',')
+        .map(|column| match column.trim() {
+            column if COLUMNS.contains(&column) => Ok(column.to_string()),
+            column => anyhow::bail!(
+                "Unknown column: {}, expected any of {}",
+                column,
+                COLUMNS.join(", ")
+            ),
+        })
+        .collect()
+}
+
+/// Checks a strftime pattern is valid and that the keys it produces sort in
+/// chronological order, as the report is ordered by its keys.
+fn validate_group_format(pattern: &str) -> Result<()> {
+    if StrftimeItems::new(pattern).any(|item| item == Item::Error) {
+        anyhow::bail!("Invalid --group-format pattern: {}", pattern);
+    }
+
+    let start = NaiveDate::from_ymd_opt(2019, 12, 25)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    let keys: Vec<String> = (0..3 * 366 * 4)
+        .map(|step| {
+            (start + Duration::hours(step * 6))
+                .format(pattern)
+                .to_string()
+        })
+        .collect();
+    if keys.windows(2).any(|pair| pair[0] > pair[1]) {
+        anyhow::bail!(
+            "--group-format pattern {} doesn't produce keys that sort chronologically",
+            pattern
+        );
+    }
+
+    Ok(())
+}
+
+/// Parses a UTC offset like `+01:00`, `-05:30` or `Z`.
+fn parse_offset(value: &str) -> Result<FixedOffset> {
+    DateTime::parse_from_rfc3339(&format!("2000-01-01T00:00:00{}", value))
+        .map(|date| *date.offset())
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid value for --timezone: {}, expected an offset like +01:00",
+                value
+            )
+        })
+}
+
+fn parse_value<T>(flag: &str, value: Option<String>) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value = value.ok_or_else(|| anyhow::anyhow!("Missing value for {}", flag))?;
+    value
+        .parse()
+        .with_context(|| format!("Invalid value for {}: {}", flag, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = Options::parse(args(&["acme", "--min-amount", "5.5"])).unwrap();
+
+        assert_eq!(options.client_names, vec!["acme".to_string()]);
+        assert_eq!(options.min_amount, Some(5.5));
+        assert_eq!(options.watch, None);
+
+        let options = Options::parse(args(&["--watch", "60", "acme"])).unwrap();
+        assert_eq!(options.watch, Some(60));
+        assert!(!options.strict);
+
+        let options = Options::parse(args(&["acme", "--strict"])).unwrap();
+        assert!(options.strict);
+
+        let options = Options::parse(args(&["acme", "--timeout", "5"])).unwrap();
+        assert_eq!(options.timeout, Some(5));
+
+        let options = Options::parse(args(&["acme", "--timezone", "-05:30"])).unwrap();
+        assert_eq!(options.timezone, FixedOffset::west_opt(5 * 3600 + 1800));
+        assert_eq!(
+            "Invalid value for --timezone: CET, expected an offset like +01:00",
+            Options::parse(args(&["acme", "--timezone", "CET"]))
+                .unwrap_err()
+                .to_string()
+        );
+
+        let options = Options::parse(args(&["acme", "--format", "json"])).unwrap();
+        assert_eq!(options.format, Format::Json);
+        assert!(Options::parse(args(&["acme", "--format", "xml"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_options_errors() {
+        assert_eq!(
+            "Missing value for --min-amount",
+            Options::parse(args(&["acme", "--min-amount"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "Invalid value for --min-amount: five",
+            Options::parse(args(&["acme", "--min-amount", "five"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "Unknown option: --nope",
+            Options::parse(args(&["--nope"])).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "--rebill requires both --since and --until",
+            Options::parse(args(&["acme", "--rebill", "--since", "2022-01-01"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "--since 2022-02-01 is after --until 2022-01-01",
+            Options::parse(args(&[
+                "acme",
+                "--since",
+                "2022-02-01",
+                "--until",
+                "2022-01-01"
+            ]))
+            .unwrap_err()
+            .to_string()
+        );
+        assert_eq!(
+            "--quiet can't be combined with --explain or --format json",
+            Options::parse(args(&["acme", "--quiet", "--explain"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "--quiet can't be combined with --redact",
+            Options::parse(args(&["acme", "--quiet", "--redact"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "--mark-billed can't be combined with --format, --html, --pdf or --output-dir",
+            Options::parse(args(&["acme", "--mark-billed", "--html", "invoice.html"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(Options::parse(args(&["acme", "--mark-billed", "--format", "json"])).is_err());
+        assert_eq!(
+            "--save-baseline and --diff-baseline take a single client",
+            Options::parse(args(&["acme", "globex", "--diff-baseline", "base.json"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "--format json, --html, --pdf, --explain, --forecast, --entries, --by-task, --by-project, --by-weekday, --categorize and --summary-json take a single client",
+            Options::parse(args(&["acme", "globex", "--format", "json"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(Options::parse(args(&["acme", "globex", "--by-task"])).is_err());
+        assert_eq!(
+            "--output-dir can't be combined with --html or --pdf",
+            Options::parse(args(&["--output-dir", "out", "--html", "invoice.html"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "--group-by client can't be combined with --format json, --html or --pdf",
+            Options::parse(args(&["--group-by", "client", "--format", "json"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(Options::parse(args(&["--group-by", "client", "--html", "invoice.html"])).is_err());
+        assert!(Options::parse(args(&["acme", "globex", "--html", "invoice.html"])).is_err());
+        assert!(Options::parse(args(&[
+            "acme",
+            "globex",
+            "--format",
+            "json",
+            "--output-dir",
+            "out"
+        ]))
+        .is_ok());
+        assert_eq!(
+            "--invoice-number requires --html or --pdf",
+            Options::parse(args(&["acme", "--invoice-number", "7"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "--quiet can't be combined with --entries, --by-task, --by-project, --by-weekday, --categorize or --summary-json",
+            Options::parse(args(&["acme", "--quiet", "--by-task"]))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "--watch interval must be at least 10 seconds",
+            Options::parse(args(&["acme", "--watch", "1"]))
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_period_range() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let today = date(2023, 3, 15);
+
+        assert_eq!(
+            (date(2023, 2, 1), date(2023, 2, 28)),
+            Period::LastMonth.range(today)
+        );
+        assert_eq!(
+            (date(2022, 12, 1), date(2022, 12, 31)),
+            Period::LastMonth.range(date(2023, 1, 10))
+        );
+        assert_eq!(
+            (date(2023, 1, 1), date(2023, 3, 15)),
+            Period::ThisYear.range(today)
+        );
+        assert_eq!(
+            (date(2023, 3, 9), date(2023, 3, 15)),
+            Period::Last7Days.range(today)
+        );
+
+        assert_eq!(Period::LastMonth, "last-month".parse().unwrap());
+        assert!(Options::parse(args(&["acme", "--period", "this-year"]))
+            .unwrap()
+            .since
+            .is_some());
+        assert_eq!(
+            "--period can't be combined with --since or --until",
+            Options::parse(args(&["--period", "last-month", "--since", "2022-01-01"]))
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(
+            vec!["billed_amount", "date"],
+            parse_columns("billed_amount, date").unwrap()
+        );
+        assert!(parse_columns("date,hourly_rate")
+            .unwrap_err()
+            .to_string()
+            .starts_with("Unknown column: hourly_rate, expected any of date, "));
+    }
+
+    #[test]
+    fn test_validate_group_format() {
+        assert!(validate_group_format("%Y-%m-%d").is_ok());
+        assert!(validate_group_format("%Y-%m").is_ok());
+        assert!(validate_group_format("%Y").is_ok());
+        assert_eq!(
+            "--group-format pattern %d-%m-%Y doesn't produce keys that sort chronologically",
+            validate_group_format("%d-%m-%Y").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Invalid --group-format pattern: %Q",
+            validate_group_format("%Q").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            Command::Report(Box::new(Options {
+                client_names: vec!["acme".to_string()],
+                ..Default::default()
+            })),
+            Command::parse(args(&["acme"])).unwrap()
+        );
+        assert_eq!(
+            Command::AddClient(AddClientOptions {
+                name: Some("acme".to_string()),
+                id: Some("123".to_string()),
+                hourly_rate: Some("30".to_string()),
+                force: true,
+                ..Default::default()
+            }),
+            Command::parse(args(&[
+                "clients", "add", "acme", "--id", "123", "--rate", "30", "--force"
+            ]))
+            .unwrap()
+        );
+        assert_eq!(
+            Command::MigrateConfig(ConfigOptions::default()),
+            Command::parse(args(&["config", "migrate"])).unwrap()
+        );
+        let command = Command::parse(args(&["clients", "sync", "--config", "ci.toml"])).unwrap();
+        assert_eq!(Some(Path::new("ci.toml")), command.config());
+        let command =
+            Command::parse(args(&["clients", "add", "acme", "--config", "ci.toml"])).unwrap();
+        assert_eq!(Some(Path::new("ci.toml")), command.config());
+        assert_eq!(
+            Command::ShowConfig(ShowConfigOptions {
+                config: None,
+                json: true,
+            }),
+            Command::parse(args(&["config", "show", "--json"])).unwrap()
+        );
+        assert_eq!(
+            Command::CheckConfig(ConfigOptions {
+                config: Some(PathBuf::from("ci.toml")),
+            }),
+            Command::parse(args(&["config", "check", "--config", "ci.toml"])).unwrap()
+        );
+        assert_eq!(
+            "Unknown clients subcommand: remove",
+            Command::parse(args(&["clients", "remove"]))
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
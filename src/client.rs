@@ -1,21 +1,73 @@
 use std::collections::HashMap;
 
+use crate::error::ReportError;
 use crate::{DetailsResponse, TimeEntry};
-use anyhow::Result;
-use chrono::Datelike;
-use reqwest::{blocking::Client, Method};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, NaiveDate};
+use reqwest::{blocking::Client, Method, Proxy};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use super::{Config, ReportDetails};
 
+/// Toggl host used unless `base_url` is configured, e.g. for a proxy.
+pub const DEFAULT_BASE_URL: &str = "https://api.track.toggl.com";
+const DETAILS_PATH: &str = "/reports/api/v2/details";
+/// Clients of a workspace, `{}` being the workspace id.
+const CLIENTS_PATH: &str = "/api/v9/workspaces/{}/clients";
+const WORKSPACES_PATH: &str = "/api/v9/workspaces";
+/// Number of entries requested per page of the details endpoint.
+const PAGE_SIZE: u32 = 50;
+/// Longest we're willing to wait for a rate limit window to reset.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+/// Timeout of a single request unless configured otherwise.
+pub const DEFAULT_TIMEOUT_SECONDS: u64 = 30;
+/// Most years fetched for a report unless raised with `--max-years`, a guard against
+/// a misconfigured `start_of_time` sending hundreds of requests.
+const DEFAULT_MAX_YEARS: usize = 10;
+/// How long the years saved by a failed pull are resumed from, later they're fetched
+/// again as the entries may have changed since.
+const RESUME_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// Attempts made for a request that times out or fails to connect.
+const MAX_ATTEMPTS: u32 = 3;
+/// Longest pause before the first retry of a failed request, doubled with every
+/// further attempt. The actual pause is picked at random up to it.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
 struct ReportYear {
     current: usize,
     until: usize,
+    start_month: u32,
 }
 
 impl ReportYear {
-    fn new(current: usize, until: Option<usize>) -> Self {
-        let until = until.unwrap_or(chrono::Local::now().year() as usize);
-        Self { current, until }
+    fn new(current: usize, until: Option<usize>, start_month: u32) -> Self {
+        let until =
+            until.unwrap_or_else(|| fiscal_year(chrono::Local::now().date_naive(), start_month));
+        Self {
+            current,
+            until,
+            start_month,
+        }
+    }
+}
+
+/// Returns the fiscal year a date falls in, named after the calendar year it starts in.
+fn fiscal_year(date: NaiveDate, start_month: u32) -> usize {
+    if date.month() >= start_month {
+        date.year() as usize
+    } else {
+        date.year() as usize - 1
+    }
+}
+
+fn fiscal_year_label(year: usize, start_month: u32) -> String {
+    if start_month == 1 {
+        year.to_string()
+    } else {
+        format!("FY{}", year)
     }
 }
 
@@ -26,111 +78,679 @@ impl Iterator for ReportYear {
         if self.current > self.until {
             None
         } else {
-            let current_from = format!("{}-01-01", self.current);
-            let current_to = format!("{}-12-31", self.current);
+            let year = self.current as i32;
+            let from = NaiveDate::from_ymd_opt(year, self.start_month, 1)?;
+            let to = NaiveDate::from_ymd_opt(year + 1, self.start_month, 1)?.pred_opt()?;
             self.current += 1;
-            Some((current_from, current_to))
+            Some((
+                from.format("%Y-%m-%d").to_string(),
+                to.format("%Y-%m-%d").to_string(),
+            ))
         }
     }
 }
 
-pub fn get_billable_report(config: &Config, client_name: &str) -> Result<ReportDetails> {
-    let mut full_report = ReportDetails { data: Vec::new() };
+/// A client as listed by Toggl.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct TogglClient {
+    pub id: u64,
+    pub name: String,
+}
 
-    for (since, until) in ReportYear::new(2022, None) {
-        let mut year_report = get_year_data(config, client_name, since, until)?;
-        full_report.data.append(&mut year_report);
-    }
+/// A workspace the API token has access to.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Workspace {
+    pub id: u64,
+    pub name: String,
+}
 
-    Ok(full_report)
+/// Fetches time entries from the Toggl reports API.
+pub struct Toggl {
+    http: Client,
+    page_size: u32,
+    strict: bool,
+    /// Entries of fiscal years that are already over, kept so repeated fetches only
+    /// need to hit Toggl for the current year.
+    completed_years: HashMap<(String, usize), Vec<TimeEntry>>,
+    request_count: Cell<usize>,
+    fetched_years: usize,
+    /// Set when Toggl reports no requests left in the current rate limit window.
+    rate_limited_until: Cell<Option<Instant>>,
+    /// Whether to report each fetched year on stderr.
+    progress: bool,
+    max_years: usize,
+    /// Where `--resume` keeps the years of a pull fetched so far.
+    resume_dir: Option<PathBuf>,
+    /// State of the xorshift generator jittering retry delays, so invocations that
+    /// failed together don't all retry at the same moment.
+    jitter: Cell<u64>,
+    /// [`RETRY_DELAY`], zero in tests that shouldn't wait before retrying.
+    base_retry_delay: Duration,
 }
 
-fn get_year_data(
-    config: &Config,
-    client_name: &str,
+struct Period {
+    label: String,
     since: String,
     until: String,
-) -> Result<Vec<crate::TimeEntry>> {
-    let client = &config.clients[client_name];
-    let url = "https://api.track.toggl.com/reports/api/v2/details";
+}
 
-    let mut req_query: HashMap<&str, &str> = HashMap::new();
-    req_query.insert("client_ids", &client.id);
-    req_query.insert("since", &since);
-    req_query.insert("until", &until);
+impl Toggl {
+    pub fn new(strict: bool) -> Self {
+        Self {
+            http: http_client(Duration::from_secs(DEFAULT_TIMEOUT_SECONDS), Vec::new())
+                .expect("Failed to build the HTTP client"),
+            page_size: PAGE_SIZE,
+            strict,
+            completed_years: HashMap::new(),
+            request_count: Cell::new(0),
+            fetched_years: 0,
+            rate_limited_until: Cell::new(None),
+            progress: true,
+            max_years: DEFAULT_MAX_YEARS,
+            resume_dir: None,
+            jitter: Cell::new(jitter_seed()),
+            base_retry_delay: RETRY_DELAY,
+        }
+    }
 
-    let mut entries: Vec<TimeEntry> = Vec::new();
+    /// Turns the per-year "Got N entries" lines on or off, see [`show_progress`].
+    pub fn with_progress(self, progress: bool) -> Self {
+        Self { progress, ..self }
+    }
 
-    let mut response = make_request(Method::GET, url, req_query, config).and_then(|r| {
-        serde_json::from_str::<DetailsResponse>(&r).map_err(|e| anyhow::anyhow!(e))
-    })?;
+    /// Refuses to fetch reports spanning more than `max_years` fiscal years.
+    pub fn with_max_years(self, max_years: usize) -> Self {
+        Self { max_years, ..self }
+    }
 
-    entries.append(&mut response.data);
+    /// Saves each fetched year in `dir` until the whole pull succeeded, so a pull
+    /// that failed part way only fetches the remaining years when run again.
+    pub fn with_resume_dir(self, dir: PathBuf) -> Self {
+        Self {
+            resume_dir: Some(dir),
+            ..self
+        }
+    }
+
+    /// Aborts requests that take longer than `timeout`, they're retried up to
+    /// [`MAX_ATTEMPTS`] times, and sends them through the configured proxies. Without
+    /// any, the `HTTP_PROXY`/`HTTPS_PROXY` environment variables are honored.
+    pub fn with_http_config(self, config: &Config, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            http: http_client(timeout, proxies(config)?)?,
+            ..self
+        })
+    }
+
+    /// Number of HTTP requests made to Toggl so far.
+    pub fn request_count(&self) -> usize {
+        self.request_count.get()
+    }
+
+    /// Number of years fetched from Toggl so far, not counting cached ones.
+    pub fn fetched_years(&self) -> usize {
+        self.fetched_years
+    }
+
+    /// Starts counting requests and fetched years from zero, e.g. for every refresh
+    /// of `--watch`.
+    pub fn reset_counts(&mut self) {
+        self.request_count.set(0);
+        self.fetched_years = 0;
+    }
+
+    /// Fetches the name and id of every client in the workspace.
+    pub fn get_clients(&self, config: &Config) -> Result<Vec<TogglClient>, ReportError> {
+        self.fetch_clients(config)
+            .map_err(|err| ReportError::classify(err, ReportError::Config))
+    }
+
+    fn fetch_clients(&self, config: &Config) -> Result<Vec<TogglClient>> {
+        config.validate()?;
+        let path = CLIENTS_PATH.replace("{}", config.workspace_id.trim());
+        let url = api_url(config.base_url(), &path)?;
+        let body = self.make_request(Method::GET, url.as_str(), HashMap::new(), config)?;
+
+        // a workspace without clients is answered with null
+        let clients: Option<Vec<TogglClient>> = serde_json::from_str(&body).map_err(|e| {
+            ReportError::Parse(anyhow::anyhow!(e).context("Failed to parse Toggl clients response"))
+        })?;
+        Ok(clients.unwrap_or_default())
+    }
+
+    /// Fetches every workspace the API token has access to.
+    pub fn get_workspaces(&self, config: &Config) -> Result<Vec<Workspace>, ReportError> {
+        self.fetch_workspaces(config)
+            .map_err(|err| ReportError::classify(err, ReportError::Config))
+    }
+
+    fn fetch_workspaces(&self, config: &Config) -> Result<Vec<Workspace>> {
+        let url = api_url(config.base_url(), WORKSPACES_PATH)?;
+        let body = self.make_request(Method::GET, url.as_str(), HashMap::new(), config)?;
+
+        serde_json::from_str(&body).map_err(|e| {
+            ReportError::Parse(
+                anyhow::anyhow!(e).context("Failed to parse Toggl workspaces response"),
+            )
+            .into()
+        })
+    }
 
-    if response.total_count > 50 {
-        let mut page = 2;
-        let mut total_pages = response.total_count / 50;
-        if response.total_count % 50 > 0 {
-            total_pages += 1;
+    /// Fetches all entries of the workspace between the days, whatever their client.
+    /// The reports API covers at most a year per request.
+    pub fn get_workspace_entries(
+        &self,
+        config: &Config,
+        workspace: &Workspace,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<ReportDetails, ReportError> {
+        let config = Config {
+            workspace_id: workspace.id.to_string(),
+            ..config.clone()
+        };
+        let period = Period {
+            label: workspace.name.clone(),
+            since: since.to_string(),
+            until: until.to_string(),
+        };
+        let data = self
+            .get_details(&config, HashMap::new(), &period)
+            .map_err(|err| ReportError::classify(err, ReportError::Config))?;
+
+        Ok(ReportDetails { data })
+    }
+
+    pub fn get_billable_report(
+        &mut self,
+        config: &Config,
+        client_name: &str,
+    ) -> Result<ReportDetails, ReportError> {
+        self.fetch_billable_report(config, client_name)
+            .map_err(|err| ReportError::classify(err, ReportError::Config))
+    }
+
+    fn fetch_billable_report(
+        &mut self,
+        config: &Config,
+        client_name: &str,
+    ) -> Result<ReportDetails> {
+        config.validate()?;
+        if config.clients[client_name].ids().next().is_none() {
+            anyhow::bail!(
+                "Missing Toggl client id for {}, set its id or run `clients sync`",
+                client_name
+            );
+        }
+        let start_month = config.fiscal_year_start_month();
+
+        let mut full_report = ReportDetails { data: Vec::new() };
+        let first_year = fiscal_year(config.start_of_time()?, start_month);
+
+        let current_year = fiscal_year(chrono::Local::now().date_naive(), start_month);
+        let years = (current_year + 1).saturating_sub(first_year);
+        if years > self.max_years {
+            anyhow::bail!(
+                "start_of_time {} spans {} years, more than the {} of --max-years, raise it to fetch them all",
+                config.start_of_time()?,
+                years,
+                self.max_years
+            );
+        }
+
+        let resume_path = self
+            .resume_dir
+            .as_ref()
+            .map(|dir| resume_path(dir, config, client_name));
+        let mut resumed = match &resume_path {
+            Some(path) => ResumeState::load(path, &resume_key(config, client_name)?),
+            None => ResumeState::default(),
+        };
+
+        for (year, (since, until)) in
+            (first_year..).zip(ReportYear::new(first_year, None, start_month))
+        {
+            let key = (client_name.to_string(), year);
+            if let Some(year_report) = self.completed_years.get(&key) {
+                full_report.data.extend(year_report.iter().cloned());
+                continue;
+            }
+            if let Some(year_report) = resumed.years.remove(&year) {
+                if year < current_year {
+                    self.completed_years.insert(key, year_report.clone());
+                }
+                full_report.data.extend(year_report);
+                continue;
+            }
+
+            let period = Period {
+                label: fiscal_year_label(year, start_month),
+                since,
+                until,
+            };
+            let year_report = self.get_year_data(config, client_name, &period)?;
+            self.fetched_years += 1;
+            if let Some(path) = &resume_path {
+                resumed.years.insert(year, year_report.clone());
+                resumed.save(path)?;
+            }
+            if year < current_year {
+                self.completed_years.insert(key, year_report.clone());
+            }
+            full_report.data.extend(year_report);
+        }
+        if let Some(path) = &resume_path {
+            let _ = std::fs::remove_file(path);
+        }
+
+        Ok(full_report)
+    }
+
+    fn get_year_data(
+        &self,
+        config: &Config,
+        client_name: &str,
+        period: &Period,
+    ) -> Result<Vec<crate::TimeEntry>> {
+        let client = &config.clients[client_name];
+        let client_ids = client.ids().collect::<Vec<_>>().join(",");
+        let mut filters: HashMap<&str, &str> = HashMap::new();
+        filters.insert("client_ids", &client_ids);
+        if client.server_rounding() {
+            filters.insert("rounding", "on");
+        }
+
+        let mut entries = self.get_details(config, filters, period)?;
+        if client.server_rounding() {
+            entries.iter_mut().for_each(apply_rounded_duration);
+        }
+        Ok(entries)
+    }
+
+    /// Fetches every page of the workspace's entries in the period that match the
+    /// filters.
+    fn get_details(
+        &self,
+        config: &Config,
+        filters: HashMap<&str, &str>,
+        period: &Period,
+    ) -> Result<Vec<TimeEntry>> {
+        let url = api_url(config.base_url(), DETAILS_PATH)?;
+        let url = url.as_str();
+        let page_size = self.page_size;
+        let per_page = page_size.to_string();
+
+        let mut req_query = filters;
+        req_query.insert("since", &period.since);
+        req_query.insert("until", &period.until);
+        req_query.insert("per_page", &per_page);
+
+        let mut entries: Vec<TimeEntry> = Vec::new();
+
+        let mut response = self
+            .make_request(Method::GET, url, req_query.clone(), config)
+            .and_then(|r| parse_details(&r))?;
+
+        entries.append(&mut response.data);
+
+        if response.total_count > page_size {
+            let mut page = 2;
+            let mut total_pages = response.total_count / page_size;
+            if response.total_count % page_size > 0 {
+                total_pages += 1;
+            }
+
+            while page <= total_pages {
+                // guards against fetching on when pages overlap, e.g. after edits
+                // made mid-fetch, or the page math being off
+                if entries.len() >= response.total_count as usize {
+                    eprintln!(
+                        "Warning: Already got {} of {} entries, skipping pages {} to {}",
+                        entries.len(),
+                        response.total_count,
+                        page,
+                        total_pages
+                    );
+                    break;
+                }
+                let query_page = page.to_string();
+
+                let mut req_query = req_query.clone();
+                req_query.insert("page", &query_page);
+
+                let mut response = self
+                    .make_request(Method::GET, url, req_query, config)
+                    .and_then(|r| parse_details(&r))?;
+
+                entries.append(&mut response.data);
+                page += 1;
+            }
         }
 
-        while page <= total_pages {
-            let query_page = page.to_string();
+        check_total_count(entries.len(), response.total_count, self.strict)?;
 
-            let mut req_query: HashMap<&str, &str> = HashMap::new();
-            req_query.insert("client_ids", &client.id);
-            req_query.insert("since", &since);
-            req_query.insert("until", &until);
-            req_query.insert("page", &query_page);
+        if self.progress {
+            eprintln!(
+                "Got {} entries for {} ({} - {})",
+                response.total_count, period.label, period.since, period.until
+            );
+        }
+        Ok(entries)
+    }
 
-            let mut response = make_request(Method::GET, url, req_query, config).and_then(|r| {
-                serde_json::from_str::<DetailsResponse>(&r).map_err(|e| anyhow::anyhow!(e))
+    fn make_request(
+        &self,
+        method: Method,
+        url: &str,
+        query_params: HashMap<&str, &str>,
+        config: &Config,
+    ) -> Result<String> {
+        let token = std::env::var("TOGGL_RS_API_TOKEN")
+            .or_else(|_| std::env::var("TOGGLE_API_TOKEN"))
+            .map_err(|_| {
+                ReportError::Auth(anyhow::anyhow!(
+                    "Missing Toggl API token, set TOGGL_RS_API_TOKEN or TOGGLE_API_TOKEN"
+                ))
             })?;
+        let token = base64::encode(format!("{}:api_token", token));
 
-            entries.append(&mut response.data);
-            page += 1;
+        let mut base_params = HashMap::new();
+        base_params.insert("user_agent", "toggl-rs");
+        base_params.insert("workspace_id", &config.workspace_id);
+
+        let mut attempt = 1;
+        loop {
+            self.wait_for_rate_limit();
+            self.request_count.set(self.request_count.get() + 1);
+
+            let result = self
+                .http
+                .request(method.clone(), url)
+                .header("Authorization", format!("Basic {}", token))
+                .header("Content-Type", "application/json")
+                .query(&base_params)
+                .query(&query_params)
+                .send()
+                .and_then(|response| {
+                    self.rate_limited_until.set(
+                        rate_limit_reset(response.headers()).map(|wait| Instant::now() + wait),
+                    );
+                    let status = response.status();
+                    response.text().map(|body| (status, body))
+                });
+
+            match result {
+                Err(e) if (e.is_timeout() || e.is_connect()) && attempt < MAX_ATTEMPTS => {
+                    eprintln!(
+                        "Request to Toggl failed ({}), retrying ({}/{})",
+                        e,
+                        attempt + 1,
+                        MAX_ATTEMPTS
+                    );
+                    std::thread::sleep(self.retry_delay(attempt));
+                    attempt += 1;
+                }
+                Err(e) => return Err(ReportError::Network(anyhow::anyhow!(e)).into()),
+                Ok((status, _)) if matches!(status.as_u16(), 401 | 403) => {
+                    return Err(ReportError::Auth(anyhow::anyhow!(
+                        "Toggl rejected the API token ({})",
+                        status
+                    ))
+                    .into())
+                }
+                Ok((_, body)) => return Ok(body),
+            }
         }
     }
 
-    println!(
-        "Got {} entries for {} - {}",
-        response.total_count, since, until
-    );
-    Ok(entries)
+    /// Pause before retrying after the given failed attempt, anywhere between zero
+    /// and the exponentially growing cap ("full jitter").
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let mut state = self.jitter.get();
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        self.jitter.set(state);
+
+        let cap = self.base_retry_delay * 2u32.pow(attempt - 1);
+        Duration::from_millis(state % (cap.as_millis() as u64 + 1))
+    }
+
+    /// Sleeps until the rate limit window resets if the last response said no
+    /// requests were left, rather than running into a 429.
+    fn wait_for_rate_limit(&self) {
+        if let Some(until) = self.rate_limited_until.take() {
+            let wait = until.saturating_duration_since(Instant::now());
+            if !wait.is_zero() {
+                eprintln!(
+                    "Toggl rate limit reached, waiting {:.1}s",
+                    wait.as_secs_f64()
+                );
+                std::thread::sleep(wait);
+            }
+        }
+    }
+}
+
+/// The years of a pull fetched before it failed, see [`Toggl::with_resume_dir`].
+#[derive(Serialize, Deserialize, Default)]
+struct ResumeState {
+    /// What the years were fetched with, see [`resume_key`].
+    #[serde(default)]
+    key: String,
+    /// Seconds since the epoch when the state was last saved.
+    #[serde(default)]
+    saved_at: u64,
+    years: HashMap<usize, Vec<TimeEntry>>,
+}
+
+impl ResumeState {
+    /// The saved state, or none when there's no readable one, it was fetched with
+    /// another key or is older than [`RESUME_MAX_AGE`].
+    fn load(path: &Path, key: &str) -> Self {
+        let state: Option<Self> = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok());
+        match state {
+            Some(state)
+                if state.key == key
+                    && unix_seconds().saturating_sub(state.saved_at)
+                        <= RESUME_MAX_AGE.as_secs() =>
+            {
+                state
+            }
+            _ => Self {
+                key: key.to_string(),
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Writes the state readable by the user only, as it holds their entries.
+    fn save(&mut self, path: &Path) -> Result<()> {
+        use std::io::Write;
+
+        self.saved_at = unix_seconds();
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(path)
+            .and_then(|mut file| file.write_all(serde_json::to_string(self)?.as_bytes()))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// What decides the entries of a pull besides the years, so a state saved before
+/// `start_of_time`, server rounding or the client's ids changed isn't resumed.
+fn resume_key(config: &Config, client_name: &str) -> Result<String> {
+    let client = &config.clients[client_name];
+    Ok(format!(
+        "{} {} {}",
+        config.start_of_time()?,
+        client.server_rounding(),
+        client.ids().collect::<Vec<_>>().join(",")
+    ))
+}
+
+fn unix_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// The per-user cache directory `--resume` keeps its state in, created accessible
+/// by the user only.
+pub fn resume_dir() -> Result<PathBuf> {
+    let dirs = directories::ProjectDirs::from("rs", "", "toggl")
+        .context("Failed to find the home directory for the --resume state")?;
+    let dir = dirs.cache_dir().join("resume");
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(&dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    #[cfg(unix)]
+    std::fs::set_permissions(&dir, std::os::unix::fs::PermissionsExt::from_mode(0o700))?;
+    Ok(dir)
+}
+
+/// State file of the client's pulls, one per workspace and client.
+fn resume_path(dir: &Path, config: &Config, client_name: &str) -> PathBuf {
+    let name: String = format!("{}-{}", config.workspace_id.trim(), client_name)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    dir.join(format!("toggl-rs-resume-{}.json", name))
+}
+
+/// Builds the details endpoint on top of the configured Toggl host.
+fn api_url(base_url: &str, path: &str) -> Result<reqwest::Url> {
+    let url = reqwest::Url::parse(&format!("{}{}", base_url.trim_end_matches('/'), path))
+        .with_context(|| format!("Invalid base_url: {}", base_url))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        anyhow::bail!("Invalid base_url: {}, expected an http(s) URL", base_url);
+    }
+
+    Ok(url)
+}
+
+fn parse_details(body: &str) -> Result<DetailsResponse> {
+    serde_json::from_str(body).map_err(|e| {
+        ReportError::Parse(anyhow::anyhow!(e).context("Failed to parse Toggl details response"))
+            .into()
+    })
+}
+
+/// Moves the end of an entry to match the duration Toggl rounded it to, which is
+/// only reflected in `dur`. Entries without a usable `dur` are left as they are.
+fn apply_rounded_duration(entry: &mut TimeEntry) {
+    if let (Some(dur), Ok(start)) = (entry.dur, DateTime::parse_from_rfc3339(&entry.start)) {
+        entry.end = Some((start + chrono::Duration::milliseconds(dur)).to_rfc3339());
+    }
 }
 
-fn make_request(
-    method: Method,
-    url: &str,
-    query_params: HashMap<&str, &str>,
-    config: &Config,
-) -> Result<String> {
-    let client = Client::new();
+/// Whether progress is reported, not with `--no-progress` or when the output isn't
+/// a terminal, e.g. in a pipeline or cron job.
+pub fn show_progress<F: Fn() -> bool>(no_progress: bool, is_terminal: F) -> bool {
+    !no_progress && is_terminal()
+}
+
+/// Seeds the retry jitter from the clock and process id, so separate invocations
+/// draw different delays. Never zero, which xorshift would be stuck at.
+fn jitter_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    (nanos ^ u64::from(std::process::id()) << 32) | 1
+}
+
+fn http_client(timeout: Duration, proxies: Vec<Proxy>) -> Result<Client> {
+    let builder = proxies
+        .into_iter()
+        .fold(Client::builder(), |builder, proxy| builder.proxy(proxy));
+
+    Ok(builder.timeout(timeout).build()?)
+}
+
+fn proxies(config: &Config) -> Result<Vec<Proxy>> {
+    let mut proxies = Vec::new();
+    if let Some(url) = &config.http_proxy {
+        proxies.push(Proxy::http(url).with_context(|| format!("Invalid http_proxy: {}", url))?);
+    }
+    if let Some(url) = &config.https_proxy {
+        proxies.push(Proxy::https(url).with_context(|| format!("Invalid https_proxy: {}", url))?);
+    }
 
-    let token = base64::encode(format!("{}:api_token", std::env::var("TOGGLE_API_TOKEN")?));
+    Ok(proxies)
+}
+
+/// How long to wait before the next request according to Toggl's rate limit headers,
+/// only set once no requests are left. `X-RateLimit-Reset` is accepted both as
+/// seconds until the reset and as a unix timestamp.
+fn rate_limit_reset(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header =
+        |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.trim().parse().ok() };
+
+    if header("X-RateLimit-Remaining")? > 0 {
+        return None;
+    }
+
+    let reset = header("X-RateLimit-Reset").unwrap_or(1);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let wait = if reset > now { reset - now } else { reset };
+
+    Some(Duration::from_secs(wait).min(MAX_RATE_LIMIT_WAIT))
+}
+
+/// Compares the number of fetched entries with the `total_count` Toggl reported on
+/// the first page, a mismatch means a page was dropped or data changed mid-fetch.
+fn check_total_count(fetched: usize, total_count: u32, strict: bool) -> Result<()> {
+    if fetched == total_count as usize {
+        return Ok(());
+    }
 
-    let mut base_params = HashMap::new();
-    base_params.insert("user_agent", "toggl-rs");
-    base_params.insert("workspace_id", &config.workspace_id);
+    let message = format!(
+        "Fetched {} entries but Toggl reported a total of {}",
+        fetched, total_count
+    );
+    if strict {
+        anyhow::bail!(message);
+    }
 
-    client
-        .request(method, url)
-        .header("Authorization", format!("Basic {}", token))
-        .header("Content-Type", "application/json")
-        .query(&base_params)
-        .query(&query_params)
-        .send()
-        .and_then(|r| r.text())
-        .map_err(|e| anyhow::anyhow!(e))
+    eprintln!("Warning: {}", message);
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{test_config, MockResponse, MockServer};
+
+    fn details_page(entries: usize, total_count: u32) -> String {
+        let entry = r#"{"start":"2022-01-01T10:00:00+00:00","end":"2022-01-01T11:00:00+00:00"}"#;
+        format!(
+            r#"{{"total_count":{},"data":[{}]}}"#,
+            total_count,
+            vec![entry; entries].join(",")
+        )
+    }
+
+    fn test_period() -> Period {
+        Period {
+            label: "2022".to_string(),
+            since: "2022-01-01".to_string(),
+            until: "2022-12-31".to_string(),
+        }
+    }
 
     #[test]
     fn test_year_iterator() {
-        let mut report_year = ReportYear::new(2018, Some(2022));
+        let mut report_year = ReportYear::new(2018, Some(2022), 1);
 
         assert_eq!(
             report_year.next(),
@@ -157,7 +777,7 @@ mod tests {
 
     #[test]
     fn test_year_iterator_without_end_year() {
-        let mut report_year = ReportYear::new(2018, None);
+        let mut report_year = ReportYear::new(2018, None, 1);
 
         let current_year = chrono::Local::now().year() as usize;
         for year in 2018..=current_year {
@@ -169,4 +789,584 @@ mod tests {
 
         assert_eq!(None, report_year.next());
     }
+
+    #[test]
+    fn test_year_iterator_with_fiscal_year() {
+        let mut report_year = ReportYear::new(2022, Some(2023), 4);
+
+        assert_eq!(
+            report_year.next(),
+            Some(("2022-04-01".to_string(), "2023-03-31".to_string()))
+        );
+        assert_eq!(
+            report_year.next(),
+            Some(("2023-04-01".to_string(), "2024-03-31".to_string()))
+        );
+        assert_eq!(report_year.next(), None);
+    }
+
+    #[test]
+    fn test_fiscal_year() {
+        let date = |m| NaiveDate::from_ymd_opt(2023, m, 15).unwrap();
+
+        assert_eq!(2022, fiscal_year(date(3), 4));
+        assert_eq!(2023, fiscal_year(date(4), 4));
+        assert_eq!(2023, fiscal_year(date(3), 1));
+        assert_eq!("FY2023", fiscal_year_label(2023, 4));
+        assert_eq!("2023", fiscal_year_label(2023, 1));
+    }
+
+    #[test]
+    fn test_short_final_page_fails_total_count_check() {
+        let mut config = test_config();
+        let server = MockServer::with_bodies(vec![details_page(50, 60), details_page(8, 60)]);
+        config.base_url = Some(server.url.clone());
+        let toggl = Toggl::new(true);
+
+        let result = toggl.get_year_data(&config, "acme", &test_period());
+
+        assert_eq!(
+            "Fetched 58 entries but Toggl reported a total of 60",
+            result.unwrap_err().to_string()
+        );
+        assert_eq!(2, server.requests().len());
+    }
+
+    #[test]
+    fn test_stops_paginating_at_total_count() {
+        let mut config = test_config();
+        let server = MockServer::with_bodies(vec![details_page(60, 60), details_page(10, 60)]);
+        config.base_url = Some(server.url.clone());
+        let toggl = Toggl::new(true);
+
+        let entries = toggl
+            .get_year_data(&config, "acme", &test_period())
+            .unwrap();
+
+        assert_eq!(60, entries.len());
+        assert_eq!(1, server.requests().len());
+    }
+
+    #[test]
+    fn test_pagination_uses_page_size() {
+        let mut config = test_config();
+        let pages = vec![
+            details_page(25, 60),
+            details_page(25, 60),
+            details_page(10, 60),
+        ];
+        let server = MockServer::with_bodies(pages);
+        config.base_url = Some(server.url.clone());
+        let toggl = Toggl {
+            page_size: 25,
+            ..Toggl::new(true)
+        };
+
+        let entries = toggl
+            .get_year_data(&config, "acme", &test_period())
+            .unwrap();
+
+        assert_eq!(60, entries.len());
+        let requests = server.requests();
+        assert_eq!(3, requests.len());
+        assert!(requests.iter().all(|r| r.contains("per_page=25")));
+        assert!(requests[2].contains("page=3"));
+    }
+
+    #[test]
+    fn test_request_count() {
+        let mut config = test_config();
+        let server = MockServer::start(|request| {
+            if request.contains("since=2022-01-01") && !request.contains("page=2") {
+                details_page(50, 60)
+            } else if request.contains("since=2022-01-01") {
+                details_page(10, 60)
+            } else {
+                details_page(0, 0)
+            }
+        });
+        config.base_url = Some(server.url.clone());
+        let mut toggl = Toggl::new(true);
+
+        toggl.get_billable_report(&config, "acme").unwrap();
+
+        let years = fiscal_year(chrono::Local::now().date_naive(), 1) - 2022 + 1;
+        assert_eq!(years, toggl.fetched_years());
+        assert_eq!(years + 1, toggl.request_count());
+        assert_eq!(server.requests().len(), toggl.request_count());
+
+        toggl.reset_counts();
+        assert_eq!((0, 0), (toggl.request_count(), toggl.fetched_years()));
+        let before = server.requests().len();
+        toggl.get_billable_report(&config, "acme").unwrap();
+        assert_eq!(server.requests().len() - before, toggl.request_count());
+    }
+
+    #[test]
+    fn test_max_years() {
+        let mut config = test_config();
+        config.start_of_time = "1970-01-01".to_string();
+        let server = MockServer::start(|_| details_page(0, 0));
+        config.base_url = Some(server.url.clone());
+
+        let err = Toggl::new(true)
+            .get_billable_report(&config, "acme")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("start_of_time 1970-01-01 spans 5"));
+        assert!(server.requests().is_empty());
+
+        let mut toggl = Toggl::new(true).with_max_years(100);
+        toggl.get_billable_report(&config, "acme").unwrap();
+        assert!(toggl.fetched_years() > 50);
+    }
+
+    #[test]
+    fn test_waits_for_rate_limit_reset() {
+        let mut config = test_config();
+        let server = MockServer::start(|request| {
+            if request.contains("page=2") {
+                MockResponse::from(details_page(10, 60))
+            } else {
+                MockResponse {
+                    headers: vec![
+                        ("X-RateLimit-Remaining", "0".to_string()),
+                        ("X-RateLimit-Reset", "1".to_string()),
+                    ],
+                    body: details_page(50, 60),
+                    ..Default::default()
+                }
+            }
+        });
+        config.base_url = Some(server.url.clone());
+        let toggl = Toggl::new(true);
+
+        let started = Instant::now();
+        toggl
+            .get_year_data(&config, "acme", &test_period())
+            .unwrap();
+
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(2, server.requests().len());
+    }
+
+    #[test]
+    fn test_retries_timed_out_request() {
+        let mut config = test_config();
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+        let server = MockServer::start(move |_| {
+            let body = details_page(10, 10);
+            if attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                MockResponse {
+                    body,
+                    delay: Duration::from_millis(150),
+                    ..Default::default()
+                }
+            } else {
+                MockResponse::from(body)
+            }
+        });
+        config.base_url = Some(server.url.clone());
+        let mut toggl = Toggl::new(true)
+            .with_http_config(&config, Duration::from_millis(100))
+            .unwrap();
+        // the retry right after the timeout is answered once the server is done
+        // stalling, 50ms later
+        toggl.base_retry_delay = Duration::ZERO;
+
+        let entries = toggl
+            .get_year_data(&config, "acme", &test_period())
+            .unwrap();
+
+        assert_eq!(10, entries.len());
+        assert_eq!(2, toggl.request_count());
+        assert_eq!(2, server.requests().len());
+    }
+
+    #[test]
+    fn test_show_progress() {
+        assert!(show_progress(false, || true));
+        assert!(!show_progress(false, || false));
+        assert!(!show_progress(true, || true));
+        assert!(!show_progress(true, || panic!(
+            "no need to check for a terminal"
+        )));
+    }
+
+    #[test]
+    fn test_retry_delay_jitter() {
+        let toggl = Toggl::new(true);
+        toggl.jitter.set(42);
+
+        let delays: Vec<Duration> = (1..=3).map(|attempt| toggl.retry_delay(attempt)).collect();
+        for (attempt, delay) in (1..=3).zip(&delays) {
+            assert!(*delay <= RETRY_DELAY * 2u32.pow(attempt - 1));
+        }
+
+        // the same seed gives the same delays
+        toggl.jitter.set(42);
+        assert_eq!(delays[0], toggl.retry_delay(1));
+        toggl.jitter.set(43);
+        assert_ne!(delays[0], toggl.retry_delay(1));
+
+        let toggl = Toggl {
+            base_retry_delay: Duration::ZERO,
+            ..Toggl::new(true)
+        };
+        assert_eq!(Duration::ZERO, toggl.retry_delay(3));
+    }
+
+    #[test]
+    fn test_rate_limit_reset() {
+        let headers = |remaining: &str, reset: &str| {
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert("X-RateLimit-Remaining", remaining.parse().unwrap());
+            headers.insert("X-RateLimit-Reset", reset.parse().unwrap());
+            headers
+        };
+
+        assert_eq!(None, rate_limit_reset(&headers("5", "10")));
+        assert_eq!(
+            Some(Duration::from_secs(10)),
+            rate_limit_reset(&headers("0", "10"))
+        );
+        assert_eq!(
+            Some(MAX_RATE_LIMIT_WAIT),
+            rate_limit_reset(&headers("0", "3600"))
+        );
+        assert_eq!(None, rate_limit_reset(&reqwest::header::HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_multiple_client_ids_query() {
+        let mut config = test_config();
+        let server = MockServer::start(|_| details_page(10, 10));
+        config.base_url = Some(server.url.clone());
+        config.clients.get_mut("acme").unwrap().id = "123, 456".to_string();
+
+        Toggl::new(true)
+            .get_year_data(&config, "acme", &test_period())
+            .unwrap();
+
+        assert!(server.requests()[0].contains("client_ids=123%2C456"));
+    }
+
+    #[test]
+    fn test_server_rounding() {
+        let mut config = test_config();
+        let server = MockServer::start(|_| {
+            r#"{"total_count":1,"data":[{"start":"2022-01-01T10:00:00+00:00","end":"2022-01-01T10:07:00+00:00","dur":900000}]}"#.to_string()
+        });
+        config.base_url = Some(server.url.clone());
+
+        let entries = Toggl::new(true)
+            .get_year_data(&config, "acme", &test_period())
+            .unwrap();
+        assert!(!server.requests()[0].contains("rounding="));
+        assert_eq!(Some("2022-01-01T10:07:00+00:00"), entries[0].end.as_deref());
+
+        config.clients.get_mut("acme").unwrap().server_rounding = Some(true);
+        let entries = Toggl::new(true)
+            .get_year_data(&config, "acme", &test_period())
+            .unwrap();
+        assert!(server.requests()[1].contains("rounding=on"));
+        assert_eq!(Some("2022-01-01T10:15:00+00:00"), entries[0].end.as_deref());
+    }
+
+    #[test]
+    fn test_get_clients() {
+        let mut config = test_config();
+        let server = MockServer::start(|_| {
+            r#"[{"id":123,"wid":42,"name":"Acme Corp"},{"id":456,"wid":42,"name":"Globex"}]"#
+                .to_string()
+        });
+        config.base_url = Some(server.url.clone());
+
+        let clients = Toggl::new(true).get_clients(&config).unwrap();
+
+        assert!(server.requests()[0].starts_with("GET /api/v9/workspaces/42/clients"));
+        assert_eq!(
+            vec![
+                TogglClient {
+                    id: 123,
+                    name: "Acme Corp".to_string()
+                },
+                TogglClient {
+                    id: 456,
+                    name: "Globex".to_string()
+                },
+            ],
+            clients
+        );
+    }
+
+    #[test]
+    fn test_missing_client_id_is_rejected_before_fetching() {
+        let mut config = test_config();
+        let server = MockServer::start(|_| details_page(10, 10));
+        config.base_url = Some(server.url.clone());
+        config.clients.get_mut("acme").unwrap().id = String::new();
+
+        let error = Toggl::new(true)
+            .get_billable_report(&config, "acme")
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("Missing Toggl client id for acme"));
+        assert!(matches!(error, ReportError::Config(_)));
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn test_resume_failed_pull() {
+        let mut config = test_config();
+        let failing = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let fail = std::sync::Arc::clone(&failing);
+        let server = MockServer::start(move |request| {
+            if request.contains("since=2023-01-01")
+                && fail.load(std::sync::atomic::Ordering::SeqCst)
+            {
+                "Bad gateway".to_string()
+            } else {
+                details_page(1, 1)
+            }
+        });
+        config.base_url = Some(server.url.clone());
+        let dir = std::env::temp_dir().join(format!("toggl-resume-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let result = Toggl::new(true)
+            .with_resume_dir(dir.clone())
+            .get_billable_report(&config, "acme");
+        assert!(result.is_err());
+        assert!(resume_path(&dir, &config, "acme").exists());
+        assert_eq!(2, server.requests().len());
+
+        failing.store(false, std::sync::atomic::Ordering::SeqCst);
+        let mut toggl = Toggl::new(true).with_resume_dir(dir.clone());
+        let report = toggl.get_billable_report(&config, "acme").unwrap();
+        let resumed = &server.requests()[2..];
+        let state_left = resume_path(&dir, &config, "acme").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // 2022 came from the state file, only 2023 on were fetched
+        assert!(resumed[0].contains("since=2023-01-01"));
+        assert!(!resumed
+            .iter()
+            .any(|request| request.contains("since=2022-01-01")));
+        assert_eq!(toggl.fetched_years(), resumed.len());
+        assert_eq!(toggl.fetched_years() + 1, report.data.len());
+        assert!(!state_left);
+    }
+
+    #[test]
+    fn test_resume_state_key_and_age() {
+        let path = std::env::temp_dir().join(format!("toggl-resume-{}.json", std::process::id()));
+        let mut state = ResumeState::load(&path, "2022-01-01 false 123");
+        state.years.insert(2022, Vec::new());
+        state.save(&path).unwrap();
+
+        assert!(ResumeState::load(&path, "2022-01-01 false 123")
+            .years
+            .contains_key(&2022));
+        assert!(ResumeState::load(&path, "2022-01-01 true 123")
+            .years
+            .is_empty());
+        assert!(ResumeState::load(&path, "2021-01-01 false 123")
+            .years
+            .is_empty());
+
+        state.save(&path).unwrap();
+        #[cfg(unix)]
+        assert_eq!(
+            0o600,
+            std::os::unix::fs::PermissionsExt::mode(
+                &std::fs::metadata(&path).unwrap().permissions()
+            ) & 0o777
+        );
+        state.saved_at = unix_seconds() - RESUME_MAX_AGE.as_secs() - 1;
+        std::fs::write(&path, serde_json::to_string(&state).unwrap()).unwrap();
+        let expired = ResumeState::load(&path, "2022-01-01 false 123");
+        std::fs::remove_file(&path).unwrap();
+        assert!(expired.years.is_empty());
+    }
+
+    #[test]
+    fn test_get_workspace_entries() {
+        let config = test_config();
+        let server = MockServer::start(|request| {
+            if request.starts_with("GET /api/v9/workspaces?") {
+                r#"[{"id":1,"name":"Personal"},{"id":2,"name":"Work"}]"#.to_string()
+            } else if request.contains("workspace_id=1&")
+                || request.ends_with("workspace_id=1 HTTP/1.1")
+            {
+                details_page(1, 1)
+            } else {
+                details_page(2, 2)
+            }
+        });
+        let config = Config {
+            base_url: Some(server.url.clone()),
+            ..config
+        };
+        let toggl = Toggl::new(true);
+        let since = NaiveDate::from_ymd_opt(2022, 1, 1).unwrap();
+        let until = NaiveDate::from_ymd_opt(2022, 12, 31).unwrap();
+
+        let workspaces = toggl.get_workspaces(&config).unwrap();
+        let entries: Vec<usize> = workspaces
+            .iter()
+            .map(|workspace| {
+                toggl
+                    .get_workspace_entries(&config, workspace, since, until)
+                    .unwrap()
+                    .data
+                    .len()
+            })
+            .collect();
+
+        assert_eq!(
+            vec!["Personal", "Work"],
+            workspaces
+                .iter()
+                .map(|w| w.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec![1, 2], entries);
+        assert!(!server.requests()[1].contains("client_ids"));
+    }
+
+    #[test]
+    fn test_report_error_kinds() {
+        let mut config = test_config();
+        let server = MockServer::start(|request| {
+            if request.contains("client_ids=123") {
+                MockResponse {
+                    status: Some(403),
+                    ..Default::default()
+                }
+            } else {
+                MockResponse::from("<html>Maintenance</html>".to_string())
+            }
+        });
+        config.base_url = Some(server.url.clone());
+
+        let error = Toggl::new(true)
+            .get_billable_report(&config, "acme")
+            .unwrap_err();
+        assert!(matches!(error, ReportError::Auth(_)), "{:?}", error);
+        assert_eq!(
+            "Toggl rejected the API token (403 Forbidden)",
+            error.to_string()
+        );
+
+        config.clients.get_mut("acme").unwrap().id = "456".to_string();
+        let error = Toggl::new(true)
+            .get_billable_report(&config, "acme")
+            .unwrap_err();
+        assert!(matches!(error, ReportError::Parse(_)), "{:?}", error);
+
+        // nothing listens on the port of a dropped listener
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        config.base_url = Some(format!("http://{}", listener.local_addr().unwrap()));
+        drop(listener);
+        let mut toggl = Toggl::new(true);
+        toggl.base_retry_delay = Duration::ZERO;
+        let error = toggl.get_clients(&config).unwrap_err();
+        assert!(matches!(error, ReportError::Network(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn test_empty_workspace_id_is_rejected_before_fetching() {
+        let mut config = test_config();
+        let server = MockServer::start(|_| details_page(10, 10));
+        config.base_url = Some(server.url.clone());
+        config.workspace_id = String::new();
+
+        let error = Toggl::new(true)
+            .get_billable_report(&config, "acme")
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("Missing workspace_id in config"));
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn test_custom_base_url() {
+        let mut config = test_config();
+        let server = MockServer::start(|_| details_page(10, 10));
+        config.base_url = Some(format!("{}/proxy/", server.url));
+
+        Toggl::new(true)
+            .get_year_data(&config, "acme", &test_period())
+            .unwrap();
+
+        assert!(server.requests()[0].starts_with("GET /proxy/reports/api/v2/details?"));
+    }
+
+    #[test]
+    fn test_http_proxy() {
+        let mut config = test_config();
+        let proxy = MockServer::start(|_| details_page(10, 10));
+        config.base_url = Some("http://toggl.invalid".to_string());
+        config.http_proxy = Some(proxy.url.clone());
+
+        Toggl::new(true)
+            .with_http_config(&config, Duration::from_secs(5))
+            .unwrap()
+            .get_year_data(&config, "acme", &test_period())
+            .unwrap();
+
+        assert!(proxy.requests()[0].starts_with("GET http://toggl.invalid/reports/api/v2/details?"));
+
+        config.https_proxy = Some("http://[::1".to_string());
+        assert_eq!(
+            "Invalid https_proxy: http://[::1",
+            Toggl::new(true)
+                .with_http_config(&config, Duration::from_secs(5))
+                .err()
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_api_url() {
+        assert_eq!(
+            "https://api.track.toggl.com/reports/api/v2/details",
+            api_url(DEFAULT_BASE_URL, DETAILS_PATH).unwrap().as_str()
+        );
+        assert_eq!(
+            "Invalid base_url: api.track.toggl.com",
+            api_url("api.track.toggl.com", DETAILS_PATH)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "Invalid base_url: ftp://example.com, expected an http(s) URL",
+            api_url("ftp://example.com", DETAILS_PATH)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_details_error() {
+        let error =
+            parse_details(r#"{"total_count":1,"data":[{"end":"2022-01-03"}]}"#).unwrap_err();
+
+        assert_eq!("Failed to parse Toggl details response", error.to_string());
+        assert!(format!("{:#}", error).contains("missing field `start`"));
+    }
+
+    #[test]
+    fn test_check_total_count() {
+        assert!(check_total_count(60, 60, true).is_ok());
+        assert!(check_total_count(58, 60, false).is_ok());
+        assert!(check_total_count(58, 60, true).is_err());
+    }
 }
@@ -0,0 +1,347 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use std::io::{BufRead, Write};
+
+use crate::cli::AddClientOptions;
+use crate::client::TogglClient;
+use crate::{Client, Config};
+
+/// Adds a client to the config, prompting on `output` and reading from `input` for
+/// any value not given on the command line. Returns the name of the new client.
+pub fn add_client<R: BufRead, W: Write>(
+    config: &mut Config,
+    options: AddClientOptions,
+    input: &mut R,
+    output: &mut W,
+) -> Result<String> {
+    let name = prompt("Name", options.name, input, output)?;
+    if name.is_empty() {
+        anyhow::bail!("Client name cannot be empty");
+    }
+    if config.clients.contains_key(&name) && !options.force {
+        anyhow::bail!(
+            "Client {} already exists, use --force to overwrite it",
+            name
+        );
+    }
+
+    let id = prompt("Toggl client id", options.id, input, output)?;
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+        anyhow::bail!("Invalid Toggl client id: {}", id);
+    }
+
+    let hourly_rate = prompt("Hourly rate (optional)", options.hourly_rate, input, output)?;
+    let hourly_rate = match hourly_rate.as_str() {
+        "" => None,
+        rate => Some(
+            rate.parse()
+                .ok()
+                .filter(|rate: &f64| rate.is_finite() && *rate >= 0.0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid hourly rate: {}", hourly_rate))?,
+        ),
+    };
+
+    let currency = prompt("Currency (optional)", options.currency, input, output)?;
+    let currency = match currency.to_uppercase() {
+        c if c.is_empty() => None,
+        c if c.len() == 3 && c.chars().all(|c| c.is_ascii_alphabetic()) => Some(c),
+        c => anyhow::bail!("Invalid currency code: {}", c),
+    };
+
+    let last_billed_date = prompt(
+        "Last billed date (YYYY-MM-DD)",
+        options.last_billed_date,
+        input,
+        output,
+    )?;
+    NaiveDate::parse_from_str(&last_billed_date, "%Y-%m-%d")
+        .with_context(|| format!("Invalid last billed date: {}", last_billed_date))?;
+
+    config.clients.insert(
+        name.clone(),
+        Client {
+            id,
+            hourly_rate,
+            currency,
+            last_billed_date,
+            ..Default::default()
+        },
+    );
+
+    Ok(name)
+}
+
+/// Outcome of `clients sync`, by client name.
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncReport {
+    /// Clients whose id was filled in, with the id.
+    pub updated: Vec<(String, u64)>,
+    /// Clients without a Toggl client of the same name.
+    pub unmatched: Vec<String>,
+    /// Clients matching several Toggl clients, left for the user to pick.
+    pub ambiguous: Vec<String>,
+}
+
+/// Fills in the ids of configured clients that have none from the Toggl clients of
+/// the same name, ignoring case. Clients with an id are left alone.
+pub fn sync_ids(config: &mut Config, toggl_clients: &[TogglClient]) -> SyncReport {
+    let mut report = SyncReport::default();
+    for (name, client) in config.clients.iter_mut() {
+        if client.ids().next().is_some() {
+            continue;
+        }
+        let matches: Vec<&TogglClient> = toggl_clients
+            .iter()
+            .filter(|toggl_client| toggl_client.name.trim().eq_ignore_ascii_case(name))
+            .collect();
+        match matches.as_slice() {
+            [] => report.unmatched.push(name.clone()),
+            [toggl_client] => {
+                client.id = toggl_client.id.to_string();
+                report.updated.push((name.clone(), toggl_client.id));
+            }
+            _ => report.ambiguous.push(name.clone()),
+        }
+    }
+
+    report
+}
+
+/// Asks which configured client to report on when none was given on the command
+/// line, by its number in the list or its name. Without a terminal to ask on, e.g.
+/// in scripts, it's an error as before.
+pub fn pick_client<R: BufRead, W: Write>(
+    config: &Config,
+    interactive: bool,
+    input: &mut R,
+    output: &mut W,
+) -> Result<String> {
+    if !interactive || config.clients.is_empty() {
+        anyhow::bail!("No client name provided");
+    }
+
+    let names: Vec<&String> = config.clients.keys().collect();
+    for (number, name) in names.iter().enumerate() {
+        writeln!(output, "{}) {}", number + 1, name)?;
+    }
+    let choice = prompt("Client", None, input, output)?;
+    match choice.parse::<usize>() {
+        Ok(number) if (1..=names.len()).contains(&number) => Ok(names[number - 1].clone()),
+        _ => config.resolve_client_name(&choice),
+    }
+}
+
+fn prompt<R: BufRead, W: Write>(
+    label: &str,
+    value: Option<String>,
+    input: &mut R,
+    output: &mut W,
+) -> Result<String> {
+    if let Some(value) = value {
+        return Ok(value);
+    }
+
+    write!(output, "{}: ", label)?;
+    output.flush()?;
+
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> AddClientOptions {
+        AddClientOptions {
+            name: Some("acme".to_string()),
+            id: Some("123".to_string()),
+            hourly_rate: Some("30".to_string()),
+            currency: Some("eur".to_string()),
+            last_billed_date: Some("2022-01-31".to_string()),
+            force: false,
+            config: None,
+        }
+    }
+
+    #[test]
+    fn test_add_client() {
+        let mut config = Config::default();
+        let name = add_client(&mut config, options(), &mut "".as_bytes(), &mut vec![]).unwrap();
+
+        assert_eq!("acme", name);
+        let client = &config.clients["acme"];
+        assert_eq!("123", client.id);
+        assert_eq!(Some(30.0), client.hourly_rate);
+        assert_eq!(Some("EUR".to_string()), client.currency);
+        assert_eq!("2022-01-31", client.last_billed_date);
+    }
+
+    #[test]
+    fn test_add_client_prompts_for_missing_values() {
+        let mut config = Config::default();
+        let options = AddClientOptions {
+            name: Some("acme".to_string()),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+
+        add_client(
+            &mut config,
+            options,
+            &mut "123\n45.5\n\n2022-01-31\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(Some(45.5), config.clients["acme"].hourly_rate);
+        assert_eq!(None, config.clients["acme"].currency);
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .starts_with("Toggl client id: Hourly rate (optional): "));
+    }
+
+    #[test]
+    fn test_sync_ids() {
+        let mut config = Config::default();
+        for (name, id) in [
+            ("acme", ""),
+            ("globex", ""),
+            ("initech", ""),
+            ("umbrella", "9"),
+        ] {
+            config.clients.insert(
+                name.to_string(),
+                Client {
+                    id: id.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        let toggl_client = |id, name: &str| TogglClient {
+            id,
+            name: name.to_string(),
+        };
+        let toggl_clients = [
+            toggl_client(123, "ACME"),
+            toggl_client(456, "Globex"),
+            toggl_client(457, "globex"),
+            toggl_client(789, "Umbrella"),
+        ];
+
+        let report = sync_ids(&mut config, &toggl_clients);
+
+        assert_eq!(
+            SyncReport {
+                updated: vec![("acme".to_string(), 123)],
+                unmatched: vec!["initech".to_string()],
+                ambiguous: vec!["globex".to_string()],
+            },
+            report
+        );
+        assert_eq!("123", config.clients["acme"].id);
+        assert_eq!("", config.clients["globex"].id);
+        assert_eq!("9", config.clients["umbrella"].id);
+    }
+
+    #[test]
+    fn test_pick_client() {
+        let mut config = Config::default();
+        add_client(&mut config, options(), &mut "".as_bytes(), &mut vec![]).unwrap();
+        let options = AddClientOptions {
+            name: Some("globex".to_string()),
+            id: Some("456".to_string()),
+            ..options()
+        };
+        add_client(&mut config, options, &mut "".as_bytes(), &mut vec![]).unwrap();
+
+        let mut output = Vec::new();
+        let name = pick_client(&config, true, &mut "2\n".as_bytes(), &mut output).unwrap();
+        assert_eq!("globex", name);
+        assert_eq!(
+            "1) acme\n2) globex\nClient: ",
+            String::from_utf8(output).unwrap()
+        );
+        let name = pick_client(&config, true, &mut "acme\n".as_bytes(), &mut vec![]).unwrap();
+        assert_eq!("acme", name);
+    }
+
+    #[test]
+    fn test_pick_client_without_terminal() {
+        let mut config = Config::default();
+        add_client(&mut config, options(), &mut "".as_bytes(), &mut vec![]).unwrap();
+
+        assert_eq!(
+            "No client name provided",
+            pick_client(&config, false, &mut "1\n".as_bytes(), &mut vec![])
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_add_client_refuses_to_overwrite() {
+        let mut config = Config::default();
+        add_client(&mut config, options(), &mut "".as_bytes(), &mut vec![]).unwrap();
+
+        assert_eq!(
+            "Client acme already exists, use --force to overwrite it",
+            add_client(&mut config, options(), &mut "".as_bytes(), &mut vec![])
+                .unwrap_err()
+                .to_string()
+        );
+
+        let options = AddClientOptions {
+            hourly_rate: Some("50".to_string()),
+            force: true,
+            ..options()
+        };
+        add_client(&mut config, options, &mut "".as_bytes(), &mut vec![]).unwrap();
+        assert_eq!(Some(50.0), config.clients["acme"].hourly_rate);
+    }
+
+    #[test]
+    fn test_add_client_validation() {
+        let invalid = [
+            (
+                AddClientOptions {
+                    id: Some("abc".to_string()),
+                    ..options()
+                },
+                "Invalid Toggl client id: abc",
+            ),
+            (
+                AddClientOptions {
+                    hourly_rate: Some("-1".to_string()),
+                    ..options()
+                },
+                "Invalid hourly rate: -1",
+            ),
+            (
+                AddClientOptions {
+                    currency: Some("euro".to_string()),
+                    ..options()
+                },
+                "Invalid currency code: EURO",
+            ),
+            (
+                AddClientOptions {
+                    last_billed_date: Some("31/01/2022".to_string()),
+                    ..options()
+                },
+                "Invalid last billed date: 31/01/2022",
+            ),
+        ];
+
+        for (options, error) in invalid {
+            let mut config = Config::default();
+            assert_eq!(
+                error,
+                add_client(&mut config, options, &mut "".as_bytes(), &mut vec![])
+                    .unwrap_err()
+                    .to_string()
+            );
+        }
+    }
+}
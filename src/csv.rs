@@ -0,0 +1,33 @@
+use std::io::Write;
+
+/// Writes a CSV record, quoting fields that hold a comma, quote or line break as
+/// RFC 4180 does. Rows go straight to `out`, so exports of any size are written
+/// without holding them in memory.
+pub fn write_record<W: Write>(out: &mut W, fields: &[&str]) -> std::io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(out, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            out.write_all(field.as_bytes())?;
+        }
+    }
+    out.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_record() {
+        let mut out = Vec::new();
+        write_record(&mut out, &["plain", "a, b", "say \"hi\"", ""]).unwrap();
+        assert_eq!(
+            "plain,\"a, b\",\"say \"\"hi\"\"\",\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Loads `KEY=VALUE` lines from a `.env` file into the process environment, if the
+/// file exists.
+///
+/// Variables that are already set take precedence over the file, so the order is:
+/// process environment, then `.env`, then the config file.
+pub fn load(path: &Path) -> Result<()> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    let vars = parse(&contents).with_context(|| format!("Failed to parse {}", path.display()))?;
+    for (key, value) in vars {
+        if std::env::var_os(&key).is_none() {
+            std::env::set_var(key, value);
+        }
+    }
+
+    Ok(())
+}
+
+fn parse(contents: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid line {}: {}", number + 1, line))?;
+
+        let value = value.trim();
+        let value = [('"', '"'), ('\'', '\'')]
+            .iter()
+            .find_map(|(open, close)| value.strip_prefix(*open)?.strip_suffix(*close))
+            .unwrap_or(value);
+
+        vars.push((key.trim().to_string(), value.to_string()));
+    }
+
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let vars = parse(
+            "# Toggl\nTOGGLE_API_TOKEN=abc123\n\nexport QUOTED = \"with spaces\"\nSINGLE='x'\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                ("TOGGLE_API_TOKEN".to_string(), "abc123".to_string()),
+                ("QUOTED".to_string(), "with spaces".to_string()),
+                ("SINGLE".to_string(), "x".to_string()),
+            ],
+            vars
+        );
+        assert_eq!(
+            "Invalid line 2: nope",
+            parse("A=1\nnope").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_load_keeps_existing_variables() {
+        let path = std::env::temp_dir().join(format!("toggl-test-{}.env", std::process::id()));
+        std::fs::write(
+            &path,
+            "TOGGL_RS_TEST_DOTENV_TOKEN=from-file\nTOGGL_RS_TEST_DOTENV_SET=from-file\n",
+        )
+        .unwrap();
+        std::env::set_var("TOGGL_RS_TEST_DOTENV_SET", "from-env");
+
+        load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            "from-file",
+            std::env::var("TOGGL_RS_TEST_DOTENV_TOKEN").unwrap()
+        );
+        assert_eq!(
+            "from-env",
+            std::env::var("TOGGL_RS_TEST_DOTENV_SET").unwrap()
+        );
+        assert!(load(&path).is_ok());
+    }
+}
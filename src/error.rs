@@ -0,0 +1,28 @@
+/// Kinds of failure of the public reporting functions, for callers that handle
+/// them differently rather than only print them.
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    /// The config is invalid, or is missing the client or a setting it needs.
+    #[error(transparent)]
+    Config(anyhow::Error),
+    /// There's no API token, or Toggl rejected it.
+    #[error(transparent)]
+    Auth(anyhow::Error),
+    /// Toggl couldn't be reached, even after retrying.
+    #[error(transparent)]
+    Network(anyhow::Error),
+    /// A response of Toggl or one of its entries couldn't be read.
+    #[error(transparent)]
+    Parse(anyhow::Error),
+    /// The client has no entries to bill.
+    #[error("No entries to bill for {0}")]
+    NoData(String),
+}
+
+impl ReportError {
+    /// The `ReportError` an internal error was raised as, or `kind` of it for
+    /// errors that weren't classified where they happened.
+    pub fn classify(err: anyhow::Error, kind: fn(anyhow::Error) -> ReportError) -> Self {
+        err.downcast::<ReportError>().unwrap_or_else(kind)
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::fmt::Write;
+
+use crate::locale::Locale;
+use crate::{BillReport, Client, Totals};
+
+/// Renders the unbilled days of a report as a standalone HTML invoice numbered
+/// `number`.
+pub fn render_html(
+    number: &str,
+    client_name: &str,
+    client: &Client,
+    bill_report: &BillReport,
+    totals: &Totals,
+    locale: Locale,
+    issued: NaiveDate,
+) -> Result<String> {
+    let currency = escape(client.currency());
+    let days: Vec<_> = bill_report.days.iter().filter(|day| !day.billed).collect();
+
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html>")?;
+    writeln!(html, "<head>")?;
+    writeln!(html, "<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>Invoice {}</title>", escape(client_name))?;
+    writeln!(
+        html,
+        "<style>body {{ font-family: sans-serif; }} td, th {{ padding: 4px 12px; text-align: right; }}</style>"
+    )?;
+    writeln!(html, "</head>")?;
+    writeln!(html, "<body>")?;
+    writeln!(html, "<h1>Invoice {}</h1>", escape(number))?;
+    writeln!(html, "<p>Client: {}</p>", escape(client_name))?;
+    writeln!(html, "<p>Date: {}</p>", issued)?;
+    if let Some(due_date) = client.due_date(issued) {
+        writeln!(html, "<p>Due date: {}</p>", due_date)?;
+    }
+    if let (Some(first), Some(last)) = (days.first(), days.last()) {
+        writeln!(html, "<p>Period: {} - {}</p>", first.date, last.date)?;
+    }
+    writeln!(html, "<table>")?;
+    writeln!(
+        html,
+        "<tr><th>Date</th><th>Minutes</th><th>Amount ({})</th></tr>",
+        currency
+    )?;
+    for day in days {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            day.date,
+            day.billed_minutes,
+            locale.format_amount(day.billed_amount)
+        )?;
+    }
+    writeln!(html, "</table>")?;
+    if totals.minimum_minutes > 0 {
+        writeln!(
+            html,
+            "<p>Invoice minimum applied: +{} minutes</p>",
+            totals.minimum_minutes
+        )?;
+    }
+    writeln!(html, "<p>Total hours: {}</p>", totals.hours)?;
+    for credit in client.pending_credits() {
+        writeln!(
+            html,
+            "<p class=\"credit\">Credit {} ({}): -{} {}</p>",
+            credit.date,
+            escape(&credit.description),
+            currency,
+            locale.format_amount(client.credit_amount(credit))
+        )?;
+    }
+    writeln!(
+        html,
+        "<p><strong>Total amount: {} {}</strong></p>",
+        currency,
+        locale.format_amount(totals.amount)
+    )?;
+    if let Some(note) = &client.note {
+        let lines: Vec<String> = note.trim_end().lines().map(escape).collect();
+        writeln!(html, "<p class=\"note\">{}</p>", lines.join("<br>"))?;
+    }
+    writeln!(html, "</body>")?;
+    writeln!(html, "</html>")?;
+
+    Ok(html)
+}
+
+/// Converts an HTML invoice to PDF by running `command <input.html> <output.pdf>`,
+/// `wkhtmltopdf` by default.
+#[cfg(feature = "pdf")]
+pub fn write_pdf(html: &str, command: &str, path: &std::path::Path) -> Result<()> {
+    use anyhow::Context;
+    use std::io::Write;
+
+    let (input, mut file) = create_temp_html()?;
+    let written = file.write_all(html.as_bytes());
+    drop(file);
+    if let Err(err) = written {
+        let _ = std::fs::remove_file(&input);
+        return Err(err.into());
+    }
+
+    let status = std::process::Command::new(command)
+        .arg(&input)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to run PDF command: {}", command));
+    let _ = std::fs::remove_file(&input);
+
+    let status = status?;
+    if !status.success() {
+        anyhow::bail!("PDF command {} failed with {}", command, status);
+    }
+
+    Ok(())
+}
+
+/// Creates a new file for the HTML handed to the PDF command, never opening one that
+/// already exists, as another user could have placed a symlink under a guessed name
+/// in the shared temp dir.
+#[cfg(feature = "pdf")]
+fn create_temp_html() -> Result<(std::path::PathBuf, std::fs::File)> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.subsec_nanos());
+    for attempt in 0..100u32 {
+        let path = std::env::temp_dir().join(format!(
+            "toggl-invoice-{}-{}-{}.html",
+            std::process::id(),
+            nanos,
+            attempt
+        ));
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    anyhow::bail!("Failed to create a temporary file for the PDF command")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BillReportDay;
+
+    fn bill_report() -> BillReport {
+        BillReport {
+            days: vec![
+                BillReportDay {
+                    date: "2022-01-01".to_string(),
+                    actual_minutes: 25,
+                    actual_seconds: 25 * 60,
+                    billed_minutes: 60,
+                    billed_amount: 30.0,
+                    billed: true,
+                    in_progress: false,
+                    over_cap_minutes: 0,
+                    carried_minutes: 0,
+                    holiday: false,
+                    entries: 0,
+                },
+                BillReportDay {
+                    date: "2022-01-02".to_string(),
+                    actual_minutes: 80,
+                    actual_seconds: 80 * 60,
+                    billed_minutes: 120,
+                    billed_amount: 60.0,
+                    billed: false,
+                    in_progress: false,
+                    over_cap_minutes: 0,
+                    carried_minutes: 0,
+                    holiday: false,
+                    entries: 0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_render_html() {
+        let client = Client {
+            hourly_rate: Some(30.0),
+            currency: Some("USD".to_string()),
+            ..Default::default()
+        };
+        let totals = Totals {
+            minutes: 120,
+            minimum_minutes: 0,
+            hours: 2,
+            amount: 60.0,
+            credited_amount: 0.0,
+            billed_amount: 30.0,
+        };
+
+        let issued = NaiveDate::from_ymd_opt(2022, 1, 31).unwrap();
+        let html = render_html(
+            "2022-7",
+            "R&D <Corp>",
+            &client,
+            &bill_report(),
+            &totals,
+            Locale::En,
+            issued,
+        )
+        .unwrap();
+
+        assert!(html.contains("<h1>Invoice 2022-7</h1>"));
+        assert!(html.contains("<p>Client: R&amp;D &lt;Corp&gt;</p>"));
+        assert!(html.contains("<p>Period: 2022-01-02 - 2022-01-02</p>"));
+        assert!(html.contains("<tr><td>2022-01-02</td><td>120</td><td>60.00</td></tr>"));
+        assert!(!html.contains("2022-01-01"));
+        assert!(html.contains("Total amount: USD 60.00"));
+        assert!(!html.contains("class=\"note\""));
+        assert!(!html.contains("class=\"credit\""));
+        assert!(html.contains("<p>Date: 2022-01-31</p>"));
+        assert!(!html.contains("Due date"));
+
+        let client = Client {
+            note: Some("Net 30\nPay to <IBAN>".to_string()),
+            last_billed_date: "2022-01-01".to_string(),
+            credits: Some(vec![crate::Credit {
+                date: "2022-01-02".to_string(),
+                value: crate::CreditValue::Amount(5.0),
+                description: "Outage".to_string(),
+            }]),
+            payment_terms_days: Some(14),
+            ..client
+        };
+        let html = render_html(
+            "2022-8",
+            "acme",
+            &client,
+            &bill_report(),
+            &totals,
+            Locale::En,
+            issued,
+        )
+        .unwrap();
+        assert!(html.contains("<p>Due date: 2022-02-14</p>"));
+        assert!(html.contains("<p class=\"note\">Net 30<br>Pay to &lt;IBAN&gt;</p>"));
+        assert!(html.contains("<p class=\"credit\">Credit 2022-01-02 (Outage): -USD 5.00</p>"));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn test_write_pdf_runs_command() {
+        let path = std::env::temp_dir().join(format!("toggl-test-{}.pdf", std::process::id()));
+
+        write_pdf("<html></html>", "cp", &path).unwrap();
+
+        assert_eq!("<html></html>", std::fs::read_to_string(&path).unwrap());
+        std::fs::remove_file(path).unwrap();
+        assert!(write_pdf("", "false", std::path::Path::new("out.pdf")).is_err());
+
+        let (first, _) = create_temp_html().unwrap();
+        let (second, _) = create_temp_html().unwrap();
+        assert_ne!(first, second);
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{BillReportDay, Client, Totals};
+
+/// Version of the JSON report's shape, bumped whenever a field is removed, renamed
+/// or changes meaning. Adding a field isn't a breaking change.
+///
+/// Version 2: `totals.amount` is what's left after credits, see `credited_amount`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The report as written by `--format json`:
+///
+/// ```json
+/// {
+///   "schema_version": 2,
+///   "client": "acme",
+///   "currency": "€",
+///   "days": [
+///     {
+///       "date": "2022-01-02",
+///       "actual_minutes": 25,
+///       "actual_seconds": 1500,
+///       "billed_minutes": 60,
+///       "billed_amount": 30.0,
+///       "billed": false,
+///       "in_progress": false,
+///       "over_cap_minutes": 0,
+///       "carried_minutes": 0,
+///       "holiday": false,
+///       "entries": 1
+///     }
+///   ],
+///   "totals": {
+///     "minutes": 60,
+///     "minimum_minutes": 0,
+///     "hours": 1,
+///     "amount": 30.0,
+///     "credited_amount": 0.0,
+///     "billed_amount": 0.0
+///   }
+/// }
+/// ```
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    schema_version: u32,
+    client: &'a str,
+    currency: &'a str,
+    days: Vec<&'a BillReportDay>,
+    totals: &'a Totals,
+}
+
+/// Renders the visible days and totals of a report as pretty-printed JSON.
+pub fn render_json(
+    client_name: &str,
+    client: &Client,
+    days: Vec<&BillReportDay>,
+    totals: &Totals,
+) -> Result<String> {
+    let report = JsonReport {
+        schema_version: SCHEMA_VERSION,
+        client: client_name,
+        currency: client.currency(),
+        days,
+        totals,
+    };
+
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_json() {
+        let client = Client {
+            hourly_rate: Some(30.0),
+            ..Default::default()
+        };
+        let day = BillReportDay {
+            date: "2022-01-02".to_string(),
+            actual_minutes: 25,
+            actual_seconds: 25 * 60,
+            billed_minutes: 60,
+            billed_amount: 30.0,
+            billed: false,
+            in_progress: false,
+            over_cap_minutes: 0,
+            carried_minutes: 0,
+            holiday: false,
+            entries: 0,
+        };
+        let totals = Totals {
+            minutes: 60,
+            minimum_minutes: 0,
+            hours: 1,
+            amount: 30.0,
+            credited_amount: 0.0,
+            billed_amount: 0.0,
+        };
+
+        let json = render_json("acme", &client, vec![&day], &totals).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            SCHEMA_VERSION,
+            value["schema_version"].as_u64().unwrap() as u32
+        );
+        assert_eq!("acme", value["client"]);
+        assert_eq!("2022-01-02", value["days"][0]["date"]);
+        assert_eq!(30.0, value["totals"]["amount"]);
+    }
+}
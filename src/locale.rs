@@ -0,0 +1,78 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Number formatting conventions for amounts shown to people.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    /// `1,234.56`
+    #[default]
+    En,
+    /// `1.234,56`
+    De,
+}
+
+impl Locale {
+    /// Formats an amount with two decimals and grouped thousands.
+    pub fn format_amount(self, amount: f64) -> String {
+        let (thousands, decimal) = match self {
+            Locale::En => (',', '.'),
+            Locale::De => ('.', ','),
+        };
+
+        let formatted = format!("{:.2}", amount.abs());
+        let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, "00"));
+
+        let mut grouped = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                grouped.push(thousands);
+            }
+            grouped.push(digit);
+        }
+
+        let sign = if amount < 0.0 && formatted != "0.00" {
+            "-"
+        } else {
+            ""
+        };
+        format!("{}{}{}{}", sign, grouped, decimal, fraction)
+    }
+}
+
+impl FromStr for Locale {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "en" => Ok(Locale::En),
+            "de" => Ok(Locale::De),
+            _ => anyhow::bail!("Unknown locale: {}, expected en or de", s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!("1,234.56", Locale::En.format_amount(1234.56));
+        assert_eq!("1.234,56", Locale::De.format_amount(1234.56));
+        assert_eq!("1,234,567.00", Locale::En.format_amount(1234567.0));
+        assert_eq!("999.50", Locale::En.format_amount(999.5));
+        assert_eq!("0,00", Locale::De.format_amount(0.0));
+        assert_eq!("-1.000,00", Locale::De.format_amount(-1000.0));
+    }
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::De, "de".parse().unwrap());
+        assert_eq!(
+            "Unknown locale: fr, expected en or de",
+            "fr".parse::<Locale>().unwrap_err().to_string()
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+
+use crate::Config;
+
+/// Fills in the defaults of fields added since the config was written, keeping
+/// every value that is already set. Returns `None` when nothing needs to change.
+pub fn migrate(original: &str) -> Result<Option<String>> {
+    let mut config: Config = toml::from_str(original).context("Failed to parse config")?;
+
+    config.fiscal_year_start_month.get_or_insert(1);
+    for client in config.clients.values_mut() {
+        let never_round_down = client.never_round_down();
+        client.never_round_down.get_or_insert(never_round_down);
+        client.rounding.get_or_insert_with(Default::default);
+    }
+
+    let migrated = toml::to_string(&config)?;
+    let original_value: toml::Value = toml::from_str(original)?;
+    let migrated_value: toml::Value = toml::from_str(&migrated)?;
+
+    if original_value == migrated_value {
+        Ok(None)
+    } else {
+        Ok(Some(migrated))
+    }
+}
+
+/// A line based diff between two configs, only listing changed lines.
+pub fn diff(original: &str, migrated: &str) -> String {
+    diff::lines(original, migrated)
+        .into_iter()
+        .filter_map(|line| match line {
+            diff::Result::Left(l) => Some(format!("- {}\n", l)),
+            diff::Result::Right(r) => Some(format!("+ {}\n", r)),
+            diff::Result::Both(..) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const OLD_CONFIG: &str = r#"workspace_id = "42"
+start_of_time = "2022-01-01"
+
+[clients.acme]
+id = "123"
+hourly_rate = 30.0
+last_billed_date = "2022-01-31"
+"#;
+
+    #[test]
+    fn test_migrate_old_config() {
+        let migrated = migrate(OLD_CONFIG).unwrap().unwrap();
+
+        let config: Config = toml::from_str(&migrated).unwrap();
+        assert_eq!("42", config.workspace_id);
+        assert_eq!(Some(1), config.fiscal_year_start_month);
+        let client = &config.clients["acme"];
+        assert_eq!(Some(30.0), client.hourly_rate);
+        assert_eq!("2022-01-31", client.last_billed_date);
+        assert_eq!(Some(true), client.never_round_down);
+        assert_eq!(Some(Default::default()), client.rounding);
+
+        assert_eq!(None, migrate(&migrated).unwrap());
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(
+            "- b = 1\n+ b = 2\n+ c = 3\n",
+            diff("a = 0\nb = 1\n", "a = 0\nb = 2\nc = 3\n")
+        );
+    }
+}
@@ -0,0 +1,392 @@
+use anyhow::Result;
+use std::str::FromStr;
+
+/// A regular expression for `--categorize`, of the commonly used subset: literals,
+/// `.`, `^`, `$`, classes like `[a-z]` and `[^\]]`, the escapes `\d`, `\w` and `\s`,
+/// also within classes, groups with `|` and the quantifiers `*`, `+` and `?`.
+/// Anything else, like `{n,m}`, `(?:...)` or lazy quantifiers, fails to parse.
+///
+/// Matching backtracks, which is fine for patterns run over entry descriptions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    source: String,
+    nodes: Vec<Node>,
+    groups: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    /// A capturing group, numbered from 1 by its opening parenthesis.
+    Group(Box<Node>, usize),
+    Alternation(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+type Captures = Vec<Option<(usize, usize)>>;
+
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut parser = Parser {
+            chars: &chars,
+            pos: 0,
+            groups: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.pos < chars.len() {
+            anyhow::bail!("Unmatched ) in pattern: {}", source);
+        }
+
+        Ok(Pattern {
+            source: source.to_string(),
+            nodes: vec![node],
+            groups: parser.groups,
+        })
+    }
+}
+
+impl Pattern {
+    /// The text of the first group in the first match, or of the whole match for a
+    /// pattern without groups.
+    pub fn capture(&self, text: &str) -> Option<String> {
+        let chars: Vec<char> = text.chars().collect();
+        for start in 0..=chars.len() {
+            let mut captures: Captures = vec![None; self.groups + 1];
+            let mut end = None;
+            let matched = match_nodes(&self.nodes, &chars, start, &mut captures, &mut |pos, _| {
+                end = Some(pos);
+                true
+            });
+            if matched {
+                let (from, to) = match self.groups {
+                    0 => (start, end.unwrap_or(start)),
+                    _ => captures[1]?,
+                };
+                return Some(chars[from..to].iter().collect());
+            }
+        }
+        None
+    }
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    pos: usize,
+    groups: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn alternation(&mut self) -> Result<Node> {
+        let mut branches = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.sequence()?);
+        }
+        Ok(Node::Alternation(branches))
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{')
+                    if self
+                        .chars
+                        .get(self.pos + 1)
+                        .is_some_and(char::is_ascii_digit) =>
+                {
+                    anyhow::bail!("Counted repetition like {{n,m}} isn't supported, use * or +")
+                }
+                _ => {
+                    nodes.push(atom);
+                    continue;
+                }
+            };
+            self.pos += 1;
+            if matches!(atom, Node::Start | Node::End) {
+                anyhow::bail!("Nothing to repeat before {}", self.chars[self.pos - 1]);
+            }
+            if let Some(c @ ('*' | '+' | '?')) = self.peek() {
+                anyhow::bail!(
+                    "Quantifier {}{} isn't supported, only *, + and ? are",
+                    self.chars[self.pos - 1],
+                    c
+                );
+            }
+            nodes.push(Node::Repeat {
+                node: Box::new(atom),
+                min,
+                max,
+            });
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        Ok(match self.next() {
+            Some('(') if self.peek() == Some('?') => {
+                anyhow::bail!("Groups like (?:...) aren't supported, only capturing (...) ones")
+            }
+            Some('(') => {
+                self.groups += 1;
+                let index = self.groups;
+                let inner = self.alternation()?;
+                if self.next() != Some(')') {
+                    anyhow::bail!("Unclosed ( in pattern");
+                }
+                Node::Group(Box::new(inner), index)
+            }
+            Some('[') => self.class()?,
+            Some('.') => Node::Any,
+            Some('^') => Node::Start,
+            Some('$') => Node::End,
+            Some('\\') => self.escape()?,
+            Some(c @ ('*' | '+' | '?')) => anyhow::bail!("Nothing to repeat before {}", c),
+            Some(c) => Node::Char(c),
+            None => anyhow::bail!("Unexpected end of pattern"),
+        })
+    }
+
+    fn escape(&mut self) -> Result<Node> {
+        Ok(match self.next() {
+            Some(c) => match shorthand(c.to_ascii_lowercase()) {
+                Some(ranges) => Node::Class {
+                    ranges,
+                    negated: c.is_ascii_uppercase(),
+                },
+                None => Node::Char(c),
+            },
+            None => anyhow::bail!("Pattern ends with a \\"),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = Vec::new();
+        loop {
+            let from = match self.next() {
+                // a ] right at the start is taken literally
+                Some(']') if !ranges.is_empty() => break,
+                Some('\\') => match self.next() {
+                    Some(c @ ('d' | 'w' | 's')) => {
+                        ranges.extend(shorthand(c).unwrap_or_default());
+                        continue;
+                    }
+                    Some(c @ ('D' | 'W' | 'S')) => {
+                        anyhow::bail!("\\{} isn't supported within a class", c)
+                    }
+                    c => c,
+                },
+                c => c,
+            }
+            .ok_or_else(|| anyhow::anyhow!("Unclosed [ in pattern"))?;
+            let to = match (self.peek(), self.chars.get(self.pos + 1)) {
+                (Some('-'), Some(&to)) if to != ']' => {
+                    self.pos += 2;
+                    to
+                }
+                _ => from,
+            };
+            ranges.push((from, to));
+        }
+        Ok(Node::Class { ranges, negated })
+    }
+}
+
+/// The ranges of the `\d`, `\w` and `\s` escapes.
+fn shorthand(c: char) -> Option<Vec<(char, char)>> {
+    match c {
+        'd' => Some(vec![('0', '9')]),
+        'w' => Some(vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')]),
+        's' => Some(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')]),
+        _ => None,
+    }
+}
+
+/// Matches the nodes from `pos` on, calling `k` with where the match ends to
+/// continue with whatever follows, and backtracking when it returns false.
+fn match_nodes(
+    nodes: &[Node],
+    input: &[char],
+    pos: usize,
+    captures: &mut Captures,
+    k: &mut dyn FnMut(usize, &mut Captures) -> bool,
+) -> bool {
+    let Some((node, rest)) = nodes.split_first() else {
+        return k(pos, captures);
+    };
+    match node {
+        Node::Char(_) | Node::Any | Node::Class { .. } => match input.get(pos) {
+            Some(&c) if matches_char(node, c) => match_nodes(rest, input, pos + 1, captures, k),
+            _ => false,
+        },
+        Node::Start => pos == 0 && match_nodes(rest, input, pos, captures, k),
+        Node::End => pos == input.len() && match_nodes(rest, input, pos, captures, k),
+        Node::Group(inner, index) => {
+            let index = *index;
+            match_nodes(
+                std::slice::from_ref(inner),
+                input,
+                pos,
+                captures,
+                &mut |end, captures| {
+                    let saved = captures[index];
+                    captures[index] = Some((pos, end));
+                    if match_nodes(rest, input, end, captures, k) {
+                        return true;
+                    }
+                    captures[index] = saved;
+                    false
+                },
+            )
+        }
+        Node::Alternation(branches) => branches.iter().any(|branch| {
+            match_nodes(branch, input, pos, captures, &mut |end, captures| {
+                match_nodes(rest, input, end, captures, k)
+            })
+        }),
+        Node::Repeat { node, min, max } => {
+            match_repeat(node, (*min, *max), 0, rest, input, pos, captures, k)
+        }
+    }
+}
+
+/// Greedily matches `node` as often as it can, backing off one repetition at a time.
+#[allow(clippy::too_many_arguments)]
+fn match_repeat(
+    node: &Node,
+    (min, max): (usize, Option<usize>),
+    count: usize,
+    rest: &[Node],
+    input: &[char],
+    pos: usize,
+    captures: &mut Captures,
+    k: &mut dyn FnMut(usize, &mut Captures) -> bool,
+) -> bool {
+    if max.is_none_or(|max| count < max) {
+        let matched = match_nodes(
+            std::slice::from_ref(node),
+            input,
+            pos,
+            captures,
+            &mut |end, captures| {
+                // an empty repetition would loop forever
+                end > pos
+                    && match_repeat(node, (min, max), count + 1, rest, input, end, captures, k)
+            },
+        );
+        if matched {
+            return true;
+        }
+    }
+    count >= min && match_nodes(rest, input, pos, captures, k)
+}
+
+fn matches_char(node: &Node, c: char) -> bool {
+    match node {
+        Node::Char(expected) => c == *expected,
+        Node::Any => c != '\n',
+        Node::Class { ranges, negated } => {
+            ranges.iter().any(|&(from, to)| (from..=to).contains(&c)) != *negated
+        }
+        _ => false,
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(pattern: &str, text: &str) -> Option<String> {
+        pattern.parse::<Pattern>().unwrap().capture(text)
+    }
+
+    #[test]
+    fn test_capture() {
+        assert_eq!(
+            Some("dev".to_string()),
+            capture(r"^\[(\w+)\]", "[dev] Fix login")
+        );
+        assert_eq!(None, capture(r"^\[(\w+)\]", "Fix [dev] login"));
+        assert_eq!(
+            Some("mtg".to_string()),
+            capture(r"\[([^\]]+)\]", "Call [mtg]")
+        );
+        assert_eq!(Some("42".to_string()), capture(r"\d+", "ticket 42 done"));
+        assert_eq!(Some("c".to_string()), capture("a(b|c)d", "xacd abd"));
+        assert_eq!(Some("aaa".to_string()), capture("(a*)a$", "aaaa"));
+        assert_eq!(Some("".to_string()), capture("x?", "y"));
+        assert_eq!(
+            Some("web-app_2".to_string()),
+            capture(r"\[([\w-]+)\]", "[web-app_2] Deploy")
+        );
+        assert_eq!(None, capture(r"^[\d\s]+$", "12 a"));
+        assert_eq!(Some("12 3".to_string()), capture(r"^[\d\s]+$", "12 3"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("(dev".parse::<Pattern>().is_err());
+        assert!("dev)".parse::<Pattern>().is_err());
+        assert!("[dev".parse::<Pattern>().is_err());
+        assert!("*dev".parse::<Pattern>().is_err());
+        assert!(r"dev\".parse::<Pattern>().is_err());
+
+        let error = |pattern: &str| pattern.parse::<Pattern>().unwrap_err().to_string();
+        assert_eq!(
+            "Counted repetition like {n,m} isn't supported, use * or +",
+            error(r"\d{2,4}")
+        );
+        assert_eq!(
+            "Groups like (?:...) aren't supported, only capturing (...) ones",
+            error("(?:dev|ops)")
+        );
+        assert_eq!(
+            "Quantifier *? isn't supported, only *, + and ? are",
+            error(r"\[.*?\]")
+        );
+        assert_eq!("\\W isn't supported within a class", error(r"[\W]"));
+        // a brace not starting a count is taken literally
+        assert!("{dev}".parse::<Pattern>().is_ok());
+    }
+}
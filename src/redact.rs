@@ -0,0 +1,63 @@
+use anyhow::Result;
+use serde_json::Value;
+
+/// Shown instead of money in `--redact` output.
+pub const MASK: &str = "***";
+
+/// A stable stand-in for a client's name, so redacted reports of several clients
+/// can still be told apart. The 32-bit FNV-1a hash of the name, which unlike the
+/// standard library's hasher stays the same across Rust releases.
+pub fn client_name(name: &str) -> String {
+    let hash = name.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    format!("client-{:08x}", hash)
+}
+
+/// Replaces every `*amount` field of a JSON report with the mask, minutes are kept.
+pub fn json(json: &str) -> Result<String> {
+    fn mask(value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    if key.ends_with("amount") {
+                        *value = Value::String(MASK.to_string());
+                    } else {
+                        mask(value);
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(mask),
+            _ => {}
+        }
+    }
+
+    let mut value: Value = serde_json::from_str(json)?;
+    mask(&mut value);
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_name() {
+        assert_eq!("client-45fd71af", client_name("acme"));
+        assert_ne!(client_name("acme"), client_name("globex"));
+        assert!(!client_name("acme").contains("acme"));
+    }
+
+    #[test]
+    fn test_json() {
+        let redacted = json(
+            r#"{"days":[{"billed_minutes":60,"billed_amount":30.0}],"totals":{"amount":30.0}}"#,
+        )
+        .unwrap();
+        let value: Value = serde_json::from_str(&redacted).unwrap();
+
+        assert_eq!(60, value["days"][0]["billed_minutes"]);
+        assert_eq!(MASK, value["days"][0]["billed_amount"]);
+        assert_eq!(MASK, value["totals"]["amount"]);
+    }
+}
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Days with at most this many tracked minutes aren't billed at all.
+pub const IGNORE_THRESHOLD_MINUTES: i64 = 10;
+
+/// How a day's tracked minutes are turned into billed minutes, set per client with
+/// e.g. `rounding = "quarter_hour"`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// Ignore short days, bill at least an hour and round up to two hours when close.
+    #[default]
+    Standard,
+    /// Round any fraction up to the next quarter hour.
+    QuarterHour,
+    /// Round to tenths of an hour (6 minutes), shown as decimal hours like `1.3`.
+    Tenths,
+    /// Bill the first entry of each day at least the client's
+    /// `first_entry_minimum_minutes`, and the day's other entries as tracked.
+    FirstEntryMinimum,
+}
+
+impl FromStr for Rounding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "standard" => Ok(Rounding::Standard),
+            "quarter_hour" => Ok(Rounding::QuarterHour),
+            "tenths" => Ok(Rounding::Tenths),
+            "first_entry_minimum" => Ok(Rounding::FirstEntryMinimum),
+            _ => anyhow::bail!(
+                "Unknown rounding: {}, expected standard, quarter_hour, tenths or first_entry_minimum",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Rounding::Standard => "standard",
+            Rounding::QuarterHour => "quarter_hour",
+            Rounding::Tenths => "tenths",
+            Rounding::FirstEntryMinimum => "first_entry_minimum",
+        })
+    }
+}
+
+/// Which way increment-based rounding like `quarter_hour` goes, set per client with
+/// e.g. `round_direction = "nearest"`. `standard` rounding ignores it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundDirection {
+    #[default]
+    Up,
+    Down,
+    /// To the closest increment, halfway rounds up.
+    Nearest,
+}
+
+/// A rule of a client's `rounding_brackets`, e.g. `{ up_to_minutes = 60,
+/// bill_minutes = 60 }`. Days with at most `up_to_minutes` tracked are billed as
+/// `bill_minutes`, or as tracked when it's left out.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Bracket {
+    pub up_to_minutes: i64,
+    pub bill_minutes: Option<i64>,
+}
+
+/// Bills the minutes by the first bracket they fit in, days past the last bracket
+/// are billed as tracked.
+pub fn bracket_minutes(brackets: &[Bracket], minutes: i64) -> i64 {
+    brackets
+        .iter()
+        .find(|bracket| minutes <= bracket.up_to_minutes)
+        .and_then(|bracket| bracket.bill_minutes)
+        .unwrap_or(minutes)
+}
+
+/// Why `bracket_minutes` bills the given minutes the way it does, for `--explain`.
+pub fn explain_brackets(brackets: &[Bracket], minutes: i64) -> String {
+    match brackets
+        .iter()
+        .find(|bracket| minutes <= bracket.up_to_minutes)
+    {
+        Some(Bracket {
+            up_to_minutes,
+            bill_minutes: Some(bill_minutes),
+        }) => format!(
+            "brackets, up to {} minutes are billed as {} minutes",
+            up_to_minutes, bill_minutes
+        ),
+        Some(Bracket { up_to_minutes, .. }) => format!(
+            "brackets, up to {} minutes are billed as tracked",
+            up_to_minutes
+        ),
+        None => "brackets, past the last bracket minutes are billed as tracked".to_string(),
+    }
+}
+
+impl Rounding {
+    pub fn billable_minutes(self, minutes: i64, direction: RoundDirection) -> i64 {
+        match self {
+            Rounding::Standard => calculate_billable_minutes(minutes),
+            Rounding::QuarterHour => round_to(minutes, 15, direction),
+            Rounding::Tenths => round_to(minutes, 6, direction),
+            // the minimum is applied per entry, see `first_entry_minimums`
+            Rounding::FirstEntryMinimum => minutes,
+        }
+    }
+
+    /// Whether `billable_minutes` raises the given minutes to the one hour day minimum,
+    /// which clients with `prorate_minimums` bill as tracked instead.
+    pub fn is_minimum(self, minutes: i64) -> bool {
+        self == Rounding::Standard && (IGNORE_THRESHOLD_MINUTES + 1..60).contains(&minutes)
+    }
+
+    /// Why `billable_minutes` bills the given minutes the way it does, for `--explain`.
+    pub fn explain(self, minutes: i64, direction: RoundDirection) -> &'static str {
+        match self {
+            Rounding::Standard => match minutes {
+                0..=IGNORE_THRESHOLD_MINUTES => "standard, at most 10 minutes aren't billed",
+                11..=60 => "standard, up to an hour is billed as a full hour",
+                61..=70 => "standard, up to 70 minutes are billed as tracked",
+                71..=120 => "standard, up to two hours are rounded up to two hours",
+                _ => "standard, over two hours are billed as tracked",
+            },
+            Rounding::QuarterHour => match direction {
+                RoundDirection::Up => "quarter_hour, rounded up to the next quarter hour",
+                RoundDirection::Down => "quarter_hour, rounded down to the previous quarter hour",
+                RoundDirection::Nearest => "quarter_hour, rounded to the nearest quarter hour",
+            },
+            Rounding::Tenths => match direction {
+                RoundDirection::Up => "tenths, rounded up to the next tenth of an hour",
+                RoundDirection::Down => "tenths, rounded down to the previous tenth of an hour",
+                RoundDirection::Nearest => "tenths, rounded to the nearest tenth of an hour",
+            },
+            Rounding::FirstEntryMinimum => {
+                "first_entry_minimum, the day's first entry is billed the minimum, the rest as tracked"
+            }
+        }
+    }
+}
+
+fn calculate_billable_minutes(minutes: i64) -> i64 {
+    match minutes {
+        0..=IGNORE_THRESHOLD_MINUTES => 0,
+        11..=60 => 60,
+        61..=70 => minutes,
+        71..=120 => 120,
+        _ => minutes,
+    }
+}
+
+fn round_to(minutes: i64, increment: i64, direction: RoundDirection) -> i64 {
+    let offset = match direction {
+        RoundDirection::Up => increment - 1,
+        RoundDirection::Down => 0,
+        RoundDirection::Nearest => increment / 2,
+    };
+    (minutes + offset).div_euclid(increment) * increment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rounding() {
+        for rounding in [
+            Rounding::Standard,
+            Rounding::QuarterHour,
+            Rounding::Tenths,
+            Rounding::FirstEntryMinimum,
+        ] {
+            assert_eq!(rounding, rounding.to_string().parse().unwrap());
+        }
+        assert!("hourly".parse::<Rounding>().is_err());
+    }
+
+    #[test]
+    fn test_quarter_hour() {
+        let minutes = |m| Rounding::QuarterHour.billable_minutes(m, RoundDirection::Up);
+
+        assert_eq!(0, minutes(0));
+        assert_eq!(15, minutes(1));
+        assert_eq!(15, minutes(15));
+        assert_eq!(30, minutes(16));
+        assert_eq!(30, minutes(30));
+        assert_eq!(45, minutes(44));
+        assert_eq!(45, minutes(45));
+        assert_eq!(60, minutes(46));
+    }
+
+    #[test]
+    fn test_tenths() {
+        let minutes = |m| Rounding::Tenths.billable_minutes(m, RoundDirection::Up);
+
+        assert_eq!(0, minutes(0));
+        assert_eq!(6, minutes(1));
+        assert_eq!(6, minutes(6));
+        assert_eq!(12, minutes(7));
+        assert_eq!(30, minutes(30));
+    }
+
+    #[test]
+    fn test_round_direction() {
+        let minutes = |m, direction| Rounding::QuarterHour.billable_minutes(m, direction);
+
+        assert_eq!(15, minutes(7, RoundDirection::Up));
+        assert_eq!(15, minutes(8, RoundDirection::Up));
+        assert_eq!(0, minutes(7, RoundDirection::Down));
+        assert_eq!(0, minutes(8, RoundDirection::Down));
+        assert_eq!(15, minutes(15, RoundDirection::Down));
+        assert_eq!(0, minutes(7, RoundDirection::Nearest));
+        assert_eq!(15, minutes(8, RoundDirection::Nearest));
+        assert_eq!(15, minutes(22, RoundDirection::Nearest));
+        assert_eq!(30, minutes(23, RoundDirection::Nearest));
+
+        // standard rounding has no increment to round towards
+        assert_eq!(
+            60,
+            Rounding::Standard.billable_minutes(25, RoundDirection::Down)
+        );
+    }
+
+    #[test]
+    fn test_brackets_reproduce_standard() {
+        #[derive(Deserialize)]
+        struct Client {
+            rounding_brackets: Vec<Bracket>,
+        }
+
+        let client: Client = toml::from_str(
+            r#"
+                rounding_brackets = [
+                    { up_to_minutes = 10, bill_minutes = 0 },
+                    { up_to_minutes = 60, bill_minutes = 60 },
+                    { up_to_minutes = 70 },
+                    { up_to_minutes = 120, bill_minutes = 120 },
+                ]
+            "#,
+        )
+        .unwrap();
+
+        for minutes in 0..=300 {
+            assert_eq!(
+                Rounding::Standard.billable_minutes(minutes, RoundDirection::Up),
+                bracket_minutes(&client.rounding_brackets, minutes),
+                "{} minutes",
+                minutes
+            );
+        }
+    }
+
+    #[test]
+    fn test_rounding_from_config() {
+        #[derive(Deserialize)]
+        struct Client {
+            rounding: Rounding,
+        }
+
+        let client: Client = toml::from_str(r#"rounding = "quarter_hour""#).unwrap();
+        assert_eq!(Rounding::QuarterHour, client.rounding);
+
+        let direction: RoundDirection = toml::Value::String("nearest".to_string())
+            .try_into()
+            .unwrap();
+        assert_eq!(RoundDirection::Nearest, direction);
+    }
+}
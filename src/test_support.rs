@@ -0,0 +1,126 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{Client, Config};
+
+/// A config with a single `acme` client, billed up to 2022-01-01.
+pub fn test_config() -> Config {
+    std::env::set_var("TOGGLE_API_TOKEN", "test-token");
+
+    let mut config = Config {
+        workspace_id: "42".to_string(),
+        ..Default::default()
+    };
+    config.clients.insert(
+        "acme".to_string(),
+        Client {
+            id: "123".to_string(),
+            hourly_rate: Some(30.0),
+            last_billed_date: "2022-01-01".to_string(),
+            ..Default::default()
+        },
+    );
+    config
+}
+
+/// A server answering the details report of 2022 with the `details.json` fixture
+/// and every other year with an empty page.
+pub fn details_server() -> MockServer {
+    MockServer::start(|request| {
+        if request.contains("since=2022-01-01") {
+            include_str!("../tests/fixtures/details.json").to_string()
+        } else {
+            r#"{"total_count":0,"data":[]}"#.to_string()
+        }
+    })
+}
+
+/// A canned response of the [`MockServer`].
+#[derive(Default)]
+pub struct MockResponse {
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+    /// Status code of the response, 200 when left out.
+    pub status: Option<u16>,
+    /// How long to stall before answering, to trigger client timeouts.
+    pub delay: Duration,
+}
+
+impl From<String> for MockResponse {
+    fn from(body: String) -> Self {
+        Self {
+            body,
+            ..Default::default()
+        }
+    }
+}
+
+/// A minimal HTTP server standing in for the Toggl API in tests.
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    /// Starts a server answering every request with the response `handler` returns
+    /// for its request line (e.g. `GET /reports/api/v2/details?page=2 HTTP/1.1`).
+    /// Point a config's `base_url` at `url` to use it.
+    pub fn start<F, R>(handler: F) -> Self
+    where
+        F: Fn(&str) -> R + Send + 'static,
+        R: Into<MockResponse>,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&requests);
+
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let request_line = request.lines().next().unwrap_or_default().to_string();
+
+                let response: MockResponse = handler(&request_line).into();
+                received.lock().unwrap().push(request_line);
+                std::thread::sleep(response.delay);
+
+                let headers: String = response
+                    .headers
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}\r\n", name, value))
+                    .collect();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+                    response.status.unwrap_or(200),
+                    response.body.len(),
+                    headers,
+                    response.body
+                );
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Starts a server answering requests with the given bodies in order.
+    pub fn with_bodies(bodies: Vec<String>) -> Self {
+        let bodies = Mutex::new(bodies.into_iter());
+        Self::start(move |_| bodies.lock().unwrap().next().unwrap_or_default())
+    }
+
+    /// The request lines received so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}